@@ -0,0 +1,48 @@
+//! A game hosting the staff trainer on the right half of its window, keeping
+//! the score of the rounds on the left: `cargo run --example mini_game`.
+
+use bevy::prelude::*;
+use staff_trainer::{RoundCompleted, TrainCourse, TrainerCamera, TrainerPlugin};
+
+#[derive(Component)]
+struct Score;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugins(TrainerPlugin {
+            courses: vec![TrainCourse::TREBLE_LINES, TrainCourse::TREBLE_SPACES],
+            region: Rect::new(0.5, 0., 1., 1.),
+            spawn_camera: false,
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, score_system)
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), TrainerCamera));
+    commands.spawn((
+        TextBundle::from_section("Finish a round to score", TextStyle::default()).with_style(
+            Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(24.),
+                top: Val::Px(24.),
+                ..default()
+            },
+        ),
+        Score,
+    ));
+}
+
+fn score_system(
+    mut rounds: EventReader<RoundCompleted>,
+    mut score: Query<&mut Text, With<Score>>,
+    mut total: Local<f32>,
+) {
+    for round in rounds.read() {
+        *total += round.accuracy * round.notes.len() as f32;
+        score.single_mut().sections[0].value = format!("Score {:.0}", *total);
+    }
+}
@@ -0,0 +1,7 @@
+//! Replays a recorded session: `cargo run --example replay [recording.ron]`.
+//! Without a file the last session is played back.
+
+fn main() -> anyhow::Result<()> {
+    let path = std::env::args_os().nth(1).map(std::path::PathBuf::from);
+    staff_trainer::replay_game(path.as_deref())
+}
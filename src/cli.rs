@@ -0,0 +1,162 @@
+//! Command line of the desktop build.
+
+use std::path::PathBuf;
+
+use bevy::window::{WindowMode, WindowResolution};
+
+use crate::{storage, trainer};
+
+const USAGE: &str = "\
+Usage: staff_trainer [COMMAND] [OPTIONS]
+
+Commands:
+  (none)             Open the trainer
+  stats              Print the results stored for a profile
+  export             Print everything stored for a profile, as RON
+  help               Print this help
+
+Options:
+  --profile NAME     Practise as, or report on, this profile instead of the
+                     one picked last, it is created when opening the trainer
+  --data-dir DIR     Keep the saved files in DIR
+  --windowed         Open in a window
+  --fullscreen       Open fullscreen
+  --size WIDTHxHEIGHT
+                     Size of the window, implies --windowed
+  --course NAME      Start on this course, like BassLines
+  --notes N          Notes of the first round
+  --seed SEED        Seed of the first round, a number or any word
+  --out FILE         Write the export to FILE instead of printing it";
+
+enum Command {
+    Run,
+    Stats,
+    Export,
+    Help,
+}
+
+pub(crate) struct Cli {
+    command: Command,
+    data_dir: Option<PathBuf>,
+    profile: Option<String>,
+    fullscreen: Option<bool>,
+    size: Option<(f32, f32)>,
+    /// Course, notes and seed flags, shared with the web build's URL
+    launch: Vec<(String, String)>,
+    out: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Reads `--name value` or `--name=value` flags after the command.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter().peekable();
+        let command = match args.next_if(|arg| !arg.starts_with("--")).as_deref() {
+            None => Command::Run,
+            Some("stats") => Command::Stats,
+            Some("export") => Command::Export,
+            Some("help") => Command::Help,
+            Some(command) => anyhow::bail!("unknown command {}\n\n{}", command, USAGE),
+        };
+        let mut cli = Cli {
+            command,
+            data_dir: None,
+            profile: None,
+            fullscreen: None,
+            size: None,
+            launch: Vec::new(),
+            out: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                anyhow::bail!("unexpected argument {}", arg);
+            };
+            let (name, inline_value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow::anyhow!("--{} needs a value", name))
+            };
+            let run = matches!(cli.command, Command::Run);
+            match name {
+                "help" => cli.command = Command::Help,
+                "data-dir" => cli.data_dir = Some(value()?.into()),
+                "profile" => cli.profile = Some(value()?),
+                "out" if matches!(cli.command, Command::Export) => cli.out = Some(value()?.into()),
+                "windowed" if run => cli.fullscreen = Some(false),
+                "fullscreen" if run => cli.fullscreen = Some(true),
+                "size" if run => {
+                    let size = value()?;
+                    let length = |text: &str| {
+                        let length: f32 = text.parse().ok()?;
+                        (length.is_finite() && length > 0.).then_some(length)
+                    };
+                    let (width, height) = size
+                        .split_once('x')
+                        .and_then(|(w, h)| Some((length(w)?, length(h)?)))
+                        .ok_or_else(|| anyhow::anyhow!("sizes are like 1280x720, not {}", size))?;
+                    cli.size = Some((width, height));
+                }
+                name if run && trainer::LaunchOptions::NAMES.contains(&name) => {
+                    cli.launch.push((name.to_string(), value()?));
+                }
+                _ => anyhow::bail!("unexpected flag --{}\n\n{}", name, USAGE),
+            }
+        }
+        Ok(cli)
+    }
+
+    pub fn run(self) -> anyhow::Result<()> {
+        if let Some(dir) = self.data_dir {
+            storage::set_data_dir(dir)?;
+        }
+        let profiles = trainer::profiles::Profiles::load();
+        match self.command {
+            Command::Run => {
+                let mut launch = trainer::LaunchOptions::parse(
+                    self.launch
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str())),
+                )?;
+                launch.profile = self.profile;
+                let mut window = crate::default_window();
+                if let Some((width, height)) = self.size {
+                    window.resolution = WindowResolution::new(width, height);
+                }
+                let fullscreen = self.fullscreen.or(self.size.map(|_| false));
+                if let Some(fullscreen) = fullscreen {
+                    window.mode = if fullscreen {
+                        WindowMode::BorderlessFullscreen
+                    } else {
+                        WindowMode::Windowed
+                    };
+                }
+                crate::build_app_with(window, |app| {
+                    app.insert_resource(launch);
+                    if fullscreen.is_some() {
+                        app.insert_resource(trainer::FixedWindowMode);
+                    }
+                })
+                .run();
+            }
+            Command::Stats => {
+                let profile = profiles.find(self.profile.as_deref())?;
+                println!("{}", trainer::stats::summary(profile));
+            }
+            Command::Export => {
+                let profile = profiles.find(self.profile.as_deref())?;
+                let contents = trainer::profiles::export_contents(profile)?;
+                match self.out {
+                    Some(out) => std::fs::write(out, contents)?,
+                    None => println!("{}", contents),
+                }
+            }
+            Command::Help => println!("{}", USAGE),
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,17 @@
 use bevy::prelude::*;
 
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+mod cli;
+mod share;
+mod storage;
 mod trainer;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+pub use trainer::{
+    register_course, Answer, CourseGenerator, NoteAnswered, NoteResult, RoundCompleted,
+    TrainCourse, TrainerCamera, TrainerPlugin,
+};
 
 #[cfg(target_os = "android")]
 use jni::objects::{JObject, JValue};
@@ -34,7 +45,44 @@ pub fn enable_immersive_mode() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Opens the trainer, or runs the command given on the command line of the
+/// desktop build.
 pub fn run_game() {
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    if let Err(err) = cli::Cli::parse(std::env::args().skip(1)).and_then(cli::Cli::run) {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    }
+    #[cfg(any(target_os = "android", target_arch = "wasm32"))]
+    build_app_with(default_window(), |_| {}).run();
+}
+
+/// Replays a recorded session, the last one when no file is given.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn replay_game(path: Option<&std::path::Path>) -> anyhow::Result<()> {
+    let contents = match path {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => trainer::recording::read_last_session()
+            .ok_or_else(|| anyhow::anyhow!("no recorded session"))?,
+    };
+    let recording = trainer::recording::Recording::parse(&contents)?;
+    let mut app = build_app_with(default_window(), |app| {
+        app.insert_resource(trainer::recording::Replay::new(recording));
+    });
+    app.run();
+    Ok(())
+}
+
+fn default_window() -> Window {
+    Window {
+        title: "Staff Trainer".into(),
+        mode: bevy::window::WindowMode::BorderlessFullscreen,
+        ..default()
+    }
+}
+
+/// Resources inserted by `setup` are visible to the plugins while they build.
+fn build_app_with(window: Window, setup: impl FnOnce(&mut App)) -> App {
     #[cfg(target_os = "android")]
     {
         android_logger::init_once(
@@ -45,23 +93,38 @@ pub fn run_game() {
     }
 
     let window_plugin = WindowPlugin {
-        primary_window: Some(Window {
-            title: "Staff Trainer".into(),
-            resolution: bevy::window::WindowResolution::new(1200., 540.).with_scale_factor_override(2.),
-            mode: bevy::window::WindowMode::Fullscreen,
-            ..default()
-        }),
+        primary_window: Some(window),
         ..default()
     };
 
-    App::new()
-        .insert_resource(ClearColor(Color::srgb(0.95, 0.95, 0.95)))
-        .add_plugins(DefaultPlugins.set(window_plugin))
-        .add_plugins(trainer::TrainerPlugin)
-        .run();
+    let mut app = App::new();
+    setup(&mut app);
+    app.add_plugins(DefaultPlugins.set(window_plugin))
+        .add_plugins(trainer::TrainerPlugin {
+            course_scripts: vec!["drills/leaps.drill".to_string()],
+            ..default()
+        });
+    app
 }
 
-#[bevy_main]
+#[cfg_attr(not(target_os = "android"), bevy_main)]
 fn main() {
     run_game();
+}
+
+/// Starts the trainer in the first activity. Every file opened with the app
+/// starts another one, which hands the file over to the running trainer.
+#[cfg(target_os = "android")]
+#[no_mangle]
+fn android_main(android_app: bevy::winit::android_activity::AndroidApp) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        share::hand_over_opened_file(&android_app);
+        return;
+    }
+    let _ = bevy::winit::ANDROID_APP.set(android_app);
+    main();
+    RUNNING.store(false, Ordering::SeqCst);
 }
\ No newline at end of file
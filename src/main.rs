@@ -1,5 +1,7 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // The web build waits for the page to call `start`
+    #[cfg(not(target_arch = "wasm32"))]
     staff_trainer::run_game();
 }
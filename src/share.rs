@@ -0,0 +1,210 @@
+//! Hands files to the student and takes theirs in. Desktop saves reports and
+//! profile exports next to the saved files, the web build downloads them and
+//! Android offers them to the apps that can send text. Android also imports
+//! the files the app is opened with, the web build picks files in `web.rs`.
+
+#[cfg(target_os = "android")]
+use std::sync::Mutex;
+
+#[cfg(target_os = "android")]
+use bevy::winit::android_activity::AndroidApp;
+#[cfg(target_os = "android")]
+use jni::{
+    objects::{JObject, JString, JValue},
+    JNIEnv,
+};
+
+/// Files opened with the app while the trainer was running.
+#[cfg(target_os = "android")]
+static HANDED_OVER: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Hands `contents` over as `{file_name}.ron`, returns what became of it.
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+pub fn offer(file_name: &str, contents: &str) -> anyhow::Result<String> {
+    let key = format!("reports/{}", file_name);
+    crate::storage::write(&key, contents)?;
+    let path = crate::storage::key_path(&key).unwrap_or_default();
+    Ok(format!("Saved as {}", path.display()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn offer(file_name: &str, contents: &str) -> anyhow::Result<String> {
+    use wasm_bindgen::JsCast;
+
+    let web = |err| anyhow::anyhow!("{:?}", err);
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| anyhow::anyhow!("no document"))?;
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let parts = js_sys::Array::of1(&contents.into());
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(web)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(web)?;
+    let link: web_sys::HtmlAnchorElement = document
+        .create_element("a")
+        .map_err(web)?
+        .dyn_into()
+        .map_err(web)?;
+    link.set_href(&url);
+    link.set_download(&format!("{}.ron", file_name));
+    link.click();
+    web_sys::Url::revoke_object_url(&url).map_err(web)?;
+    Ok(format!("Downloaded as {}.ron", file_name))
+}
+
+#[cfg(target_os = "android")]
+pub fn offer(file_name: &str, contents: &str) -> anyhow::Result<String> {
+    with_activity(|env, activity| {
+        let action = env.new_string("android.intent.action.SEND")?;
+        let intent = env.new_object(
+            "android/content/Intent",
+            "(Ljava/lang/String;)V",
+            &[JValue::Object(&action)],
+        )?;
+        let mime_type = env.new_string("text/plain")?;
+        env.call_method(
+            &intent,
+            "setType",
+            "(Ljava/lang/String;)Landroid/content/Intent;",
+            &[JValue::Object(&mime_type)],
+        )?;
+        for (extra, value) in [
+            ("android.intent.extra.SUBJECT", file_name),
+            ("android.intent.extra.TEXT", contents),
+        ] {
+            let extra = env.new_string(extra)?;
+            let value = env.new_string(value)?;
+            env.call_method(
+                &intent,
+                "putExtra",
+                "(Ljava/lang/String;Ljava/lang/String;)Landroid/content/Intent;",
+                &[JValue::Object(&extra), JValue::Object(&value)],
+            )?;
+        }
+        let title = env.new_string("Send the report")?;
+        let chooser = env
+            .call_static_method(
+                "android/content/Intent",
+                "createChooser",
+                "(Landroid/content/Intent;Ljava/lang/CharSequence;)Landroid/content/Intent;",
+                &[JValue::Object(&intent), JValue::Object(&title)],
+            )?
+            .l()?;
+        env.call_method(
+            activity,
+            "startActivity",
+            "(Landroid/content/Intent;)V",
+            &[JValue::Object(&chooser)],
+        )?;
+        Ok(format!("Sending {}", file_name))
+    })
+}
+
+/// Contents of the file the app was opened with, from a file manager or a
+/// mail app.
+#[cfg(target_os = "android")]
+pub fn opened_file() -> anyhow::Result<Option<String>> {
+    with_activity(read_opened_file)
+}
+
+/// Passes the file of another activity on to the running trainer, then
+/// finishes that activity. Android starts one for every file opened with the
+/// app, the trainer itself only reads the file it was started with.
+#[cfg(target_os = "android")]
+pub fn hand_over_opened_file(android_app: &AndroidApp) {
+    let result = with_app_activity(android_app, |env, activity| {
+        let contents = read_opened_file(env, activity)?;
+        env.call_method(activity, "finish", "()V", &[])?;
+        Ok(contents)
+    });
+    match result {
+        Ok(Some(contents)) => HANDED_OVER.lock().unwrap().push(contents),
+        Ok(None) => {}
+        Err(err) => println!("Can't read the opened file: {}", err),
+    }
+}
+
+/// Files handed over since the last call.
+#[cfg(target_os = "android")]
+pub fn handed_over_files() -> Vec<String> {
+    std::mem::take(&mut *HANDED_OVER.lock().unwrap())
+}
+
+#[cfg(target_os = "android")]
+fn read_opened_file(env: &mut JNIEnv, activity: &JObject) -> anyhow::Result<Option<String>> {
+    let intent = env
+        .call_method(activity, "getIntent", "()Landroid/content/Intent;", &[])?
+        .l()?;
+    let uri = env
+        .call_method(&intent, "getData", "()Landroid/net/Uri;", &[])?
+        .l()?;
+    if uri.is_null() {
+        return Ok(None);
+    }
+    let resolver = env
+        .call_method(
+            activity,
+            "getContentResolver",
+            "()Landroid/content/ContentResolver;",
+            &[],
+        )?
+        .l()?;
+    let stream = env
+        .call_method(
+            &resolver,
+            "openInputStream",
+            "(Landroid/net/Uri;)Ljava/io/InputStream;",
+            &[JValue::Object(&uri)],
+        )?
+        .l()?;
+    // A scanner delimited by the start of input reads the whole stream
+    let charset = env.new_string("UTF-8")?;
+    let scanner = env.new_object(
+        "java/util/Scanner",
+        "(Ljava/io/InputStream;Ljava/lang/String;)V",
+        &[JValue::Object(&stream), JValue::Object(&charset)],
+    )?;
+    let delimiter = env.new_string("\\A")?;
+    env.call_method(
+        &scanner,
+        "useDelimiter",
+        "(Ljava/lang/String;)Ljava/util/Scanner;",
+        &[JValue::Object(&delimiter)],
+    )?;
+    let mut contents = String::new();
+    if env.call_method(&scanner, "hasNext", "()Z", &[])?.z()? {
+        let text = JString::from(
+            env.call_method(&scanner, "next", "()Ljava/lang/String;", &[])?
+                .l()?,
+        );
+        contents = env.get_string(&text)?.into();
+    }
+    env.call_method(&scanner, "close", "()V", &[])?;
+    Ok(Some(contents))
+}
+
+#[cfg(target_os = "android")]
+fn with_activity<T>(
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let android_app = bevy::winit::ANDROID_APP
+        .get()
+        .ok_or_else(|| anyhow::anyhow!("no Android app"))?;
+    with_app_activity(android_app, f)
+}
+
+#[cfg(target_os = "android")]
+fn with_app_activity<T>(
+    android_app: &AndroidApp,
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let vm = unsafe { jni::JavaVM::from_raw(android_app.vm_as_ptr() as *mut *const _)? };
+    let activity = unsafe { JObject::from_raw(android_app.activity_as_ptr() as *mut _) };
+    let mut env = vm.attach_current_thread()?;
+    let result = f(&mut env, &activity);
+    // Java exceptions would otherwise fail the next call
+    if result.is_err() {
+        env.exception_clear()?;
+    }
+    result
+}
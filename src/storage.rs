@@ -0,0 +1,133 @@
+//! Small key/value store for persisted data. Desktop keeps one file per key in
+//! the user config directory, Android in the app's internal storage and the web
+//! build in `localStorage`.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::PathBuf};
+
+const APP_DIR: &str = "staff_trainer";
+
+#[cfg(target_os = "android")]
+fn data_dir() -> Option<PathBuf> {
+    let android_app = bevy::winit::ANDROID_APP.get()?;
+    Some(android_app.internal_data_path()?.join(APP_DIR))
+}
+
+/// Directory given on the command line
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+static DATA_DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+
+/// Keeps everything in `dir` instead of the user config directory, must be
+/// called before anything is read.
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+pub fn set_data_dir(dir: PathBuf) -> anyhow::Result<()> {
+    DATA_DIR
+        .set(dir)
+        .map_err(|_| anyhow::anyhow!("the data directory is already set"))
+}
+
+#[cfg(not(any(test, target_os = "android", target_arch = "wasm32")))]
+fn data_dir() -> Option<PathBuf> {
+    if let Some(dir) = DATA_DIR.get() {
+        return Some(dir.clone());
+    }
+    Some(dirs::config_dir()?.join(APP_DIR))
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_DATA_DIR: std::cell::RefCell<Option<PathBuf>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Tests keep away from the user's data, each test in a directory of its own.
+#[cfg(all(test, not(any(target_os = "android", target_arch = "wasm32"))))]
+fn data_dir() -> Option<PathBuf> {
+    TEST_DATA_DIR.with_borrow(Clone::clone)
+}
+
+/// Temporary data directory of the test running on this thread, removed on
+/// drop. Systems must run on the test's thread to see it.
+#[cfg(test)]
+pub struct TestDataDir(PathBuf);
+
+#[cfg(test)]
+impl TestDataDir {
+    pub fn new() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "{APP_DIR}_test_{}_{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        TEST_DATA_DIR.set(Some(dir.clone()));
+        TestDataDir(dir)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDataDir {
+    fn drop(&mut self) {
+        TEST_DATA_DIR.set(None);
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// File holding the key.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn key_path(key: &str) -> Option<PathBuf> {
+    Some(data_dir()?.join(format!("{key}.ron")))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(key: &str) -> Option<String> {
+    fs::read_to_string(key_path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(key: &str, contents: &str) -> anyhow::Result<()> {
+    let path = key_path(key).ok_or_else(|| anyhow::anyhow!("no data directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn remove(key: &str) -> anyhow::Result<()> {
+    let path = key_path(key).ok_or_else(|| anyhow::anyhow!("no data directory"))?;
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(key: &str) -> Option<String> {
+    local_storage()?
+        .get_item(&format!("{APP_DIR}/{key}"))
+        .ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(key: &str, contents: &str) -> anyhow::Result<()> {
+    let storage = local_storage().ok_or_else(|| anyhow::anyhow!("no localStorage"))?;
+    storage
+        .set_item(&format!("{APP_DIR}/{key}"), contents)
+        .map_err(|err| anyhow::anyhow!("localStorage: {:?}", err))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn remove(key: &str) -> anyhow::Result<()> {
+    let storage = local_storage().ok_or_else(|| anyhow::anyhow!("no localStorage"))?;
+    storage
+        .remove_item(&format!("{APP_DIR}/{key}"))
+        .map_err(|err| anyhow::anyhow!("localStorage: {:?}", err))
+}
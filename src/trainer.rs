@@ -1,11 +1,48 @@
-use std::str::FromStr;
-
 use bevy::{prelude::*, window::WindowResized};
-use rand::seq::SliceRandom;
-use strum::IntoEnumIterator;
-use strum_macros::{AsRefStr, EnumIter, EnumString};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+mod accessibility;
+mod calendar;
+mod config;
+mod courses;
+mod curriculum;
+mod embed;
+mod engraving;
+mod flash;
+mod gamepad;
+mod homework;
+mod layout;
+mod mistakes;
+mod naming;
+mod pointer;
+pub(crate) mod profiles;
+pub(crate) mod recording;
+mod script;
+mod scripted;
+mod settings;
+mod sound;
+pub(crate) mod stats;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod versus;
+mod xapi;
 
-const PITCH_NAMES: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+pub use config::TrainerCamera;
+use config::{TrainerAssets, TrainerConfig, TrainerGizmos};
+pub use courses::{register_course, Answer, CourseGenerator, TrainCourse};
+pub(crate) use embed::{HostCommand, LaunchOptions};
+use layout::{AnswerKey, ScaledUi, StaffLayout};
+use naming::{pitch_class, NameFont, NameLabel, NoteNaming};
+use recording::{InputSource, Recorder, Replay};
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+pub(crate) use settings::FixedWindowMode;
+use settings::{DrillMode, FeedbackStyle, Settings};
+use stats::TrainStats;
+use theme::{Inked, Palette, WRONG_MARK};
+use versus::{Versus, VersusMode};
 
 const STAFF_ELEM_IDX_TREBLE: usize = 0;
 const STAFF_ELEM_IDX_BASS: usize = 1;
@@ -19,36 +56,52 @@ const STAFF_ELEMENTS: [(Vec2, &str); 5] = [
     (Vec2::new(0., 0.), "mtb_images/rest_4.png"),
 ];
 
-const STAFF_WIDTH: i32 = 1200;
-const STAFF_X: i32 = -STAFF_WIDTH / 2;
-const STAFF_Y: i32 = 40;
+// Staff geometry in design units, see `StaffLayout` for the window-sized values
 const STAFF_SPACE_Y: i32 = 20;
 
 const STAFF_NOTE_X: i32 = 120;
 const STAFF_MAX_NOTES: usize = 30;
 
-#[derive(Clone, Copy, EnumString, EnumIter, AsRefStr)]
-enum TrainCourse {
-    TrebleLines,
-    TrebleSpaces,
-    TrebleAll,
-    BassLines,
-    BassSpaces,
-    BassAll,
-    All,
-}
+const RETRY_LABEL: &str = "Retry";
+const SETTINGS_LABEL: &str = "Settings";
+const LESSONS_LABEL: &str = "Lessons";
+const HOMEWORK_LABEL: &str = "Homework";
+const PROFILES_LABEL: &str = "Profiles";
+const HOT_SEAT_LABEL: &str = "Hot-seat";
+const RACE_LABEL: &str = "Race";
+const RETRY_MIN_NOTES: usize = 8;
 
+#[derive(Clone)]
 struct TrainNote {
     index: usize,
     pitch: i32,
-    pressed_key: char,
+    /// Pitch class of the pressed answer key
+    pressed_key: Option<usize>,
+    /// Where the note was written on the staff in the write modes
+    placed: Option<i32>,
+    /// Answers given, more than one only with strict feedback
+    attempts: u32,
+    /// The course judged the last answer right
+    right: bool,
 }
 
-#[derive(Resource)]
+#[derive(Resource, Clone)]
 struct TrainSequence {
     notes: Vec<TrainNote>,
     next_key: usize,
     course: TrainCourse,
+    /// Seed the course notes were generated from
+    seed: u64,
+    /// Draws the seed of every round, so a whole session follows from its
+    /// first seed
+    rng: ChaCha8Rng,
+    /// The round drills the mistakes of the previous one
+    retry: bool,
+    mode: DrillMode,
+    /// Wrong answers don't move the cursor on
+    strict: bool,
+    /// Curriculum lesson the notes are drawn from, instead of the course
+    lesson: Option<usize>,
 }
 
 #[derive(Component)]
@@ -56,169 +109,484 @@ struct NoteSpriteInfo {
     index: usize,
 }
 
+#[derive(Component)]
+struct ClefSprite {
+    element: usize,
+    pitch: i32,
+}
+
 #[derive(Component)]
 struct ResolutionText;
 
-fn index_to_x(index: usize, note_space: f32) -> f32 {
-    (STAFF_X + STAFF_NOTE_X) as f32 + note_space * index as f32
+/// Row of the course buttons, followed by the other game buttons.
+#[derive(Component)]
+struct CourseBar;
+
+/// Name of the note to write in the write modes.
+#[derive(Component)]
+struct PromptText;
+
+#[derive(States, Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+enum Screen {
+    #[default]
+    Trainer,
+    Settings,
+    /// Both players answer at once on their own half of the keyboard
+    Race,
+    /// Results of a two player match
+    Winner,
+    /// List of the curriculum lessons
+    Lessons,
+    /// Explanation of the selected lesson before it starts
+    LessonIntro,
+    /// Picks, creates and manages the student profiles
+    Profiles,
+    /// Assignments imported from the teacher
+    Homework,
 }
 
-fn pitch_to_y(pitch: i32) -> f32 {
-    (STAFF_Y + pitch * STAFF_SPACE_Y / 2) as f32
+/// Systems applying the student's answers to `TrainSequence`, the staff is
+/// drawn after them so it never lags a frame behind.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+struct TrainInput;
+
+/// Root node of the course bar and the answer keyboard.
+#[derive(Component)]
+struct TrainerUi;
+
+/// Row of answer keys, hidden while notes are written on the staff.
+#[derive(Component)]
+struct AnswerKeyboard;
+
+/// An answer to a note of the round, right or wrong.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct NoteAnswered {
+    /// Position of the note in the round
+    pub index: usize,
+    /// Staff position of the note, 0 for middle C and one per line or space
+    pub pitch: i32,
+    /// Pitch class answered, 0 for C
+    pub pressed_key: usize,
+    /// This answer is right, even if earlier attempts weren't
+    pub correct: bool,
 }
 
-fn pitch_to_char(pitch: i32) -> char {
-    let index = pitch.rem_euclid(PITCH_NAMES.len() as i32) as usize;
-    PITCH_NAMES[index]
+/// A round answered to its last note, reported to the hosting page as JSON.
+#[derive(Event, Clone, Debug, Serialize)]
+pub struct RoundCompleted {
+    pub course: String,
+    pub lesson: Option<String>,
+    pub seed: u64,
+    pub retry: bool,
+    /// Share of the notes answered right at the first attempt
+    pub accuracy: f32,
+    pub notes: Vec<NoteResult>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NoteResult {
+    /// Note shown, with its octave
+    pub note: String,
+    pub answer: Option<String>,
+    pub correct: bool,
+    pub attempts: u32,
+}
+
+impl RoundCompleted {
+    fn new(train: &TrainSequence, naming: NoteNaming) -> Self {
+        let correct = train.notes.iter().filter(|n| n.is_correct()).count();
+        RoundCompleted {
+            course: train.course.as_ref().to_string(),
+            lesson: train
+                .lesson
+                .map(|lesson| curriculum::LESSONS[lesson].name.to_string()),
+            seed: train.seed,
+            retry: train.retry,
+            accuracy: correct as f32 / train.notes.len().max(1) as f32,
+            notes: train
+                .notes
+                .iter()
+                .map(|note| NoteResult {
+                    note: naming.name_with_octave(note.pitch, false),
+                    answer: note.answer_name(naming),
+                    correct: note.is_correct(),
+                    attempts: note.attempts,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A game button pressed by the student, or by a replayed recording.
+#[derive(Event, Clone, Copy)]
+struct GameAction {
+    button: GameButton,
+    source: InputSource,
+}
+
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+enum GameButton {
+    Course(TrainCourse),
+    Retry,
+    Settings,
+    /// Opens the lesson list
+    Lessons,
+    /// Starts a round of a curriculum lesson
+    Lesson(usize),
+    /// Opens the profile picker
+    Profiles,
+    /// Opens the homework list
+    Homework,
+    /// Starts a round from a given seed, for homework and the hosting page
+    Seeded {
+        course: TrainCourse,
+        notes: usize,
+        seed: u64,
+    },
+    /// Starts a two player match on the current course
+    Versus(VersusMode),
+    /// Answer key of a pitch class
+    Answer(usize),
+    /// Note written on the staff, not an actual button
+    Place(i32),
+}
+
+impl TrainNote {
+    fn new(index: usize, pitch: i32) -> Self {
+        TrainNote {
+            index,
+            pitch,
+            pressed_key: None,
+            placed: None,
+            attempts: 0,
+            right: false,
+        }
+    }
+
+    /// The last answer is right.
+    fn is_right(&self) -> bool {
+        self.right
+    }
+
+    /// Answered right at the first attempt.
+    fn is_correct(&self) -> bool {
+        self.is_right() && self.attempts <= 1
+    }
+
+    /// The note written, or the name of the key pressed. Romanized, reports
+    /// read the same whether or not the CJK font loaded.
+    fn answer_name(&self, naming: NoteNaming) -> Option<String> {
+        match (self.placed, self.pressed_key) {
+            (Some(placed), _) => Some(naming.name_with_octave(placed, false)),
+            (None, Some(key)) => Some(naming.names(false)[key].to_string()),
+            (None, None) => None,
+        }
+    }
 }
 
 impl TrainSequence {
-    fn new() -> Self {
+    fn new(session_seed: u64) -> Self {
         TrainSequence {
             notes: vec![],
             next_key: 0,
-            course: TrainCourse::All,
+            course: TrainCourse::ALL,
+            seed: 0,
+            rng: ChaCha8Rng::seed_from_u64(session_seed),
+            retry: false,
+            mode: DrillMode::Read,
+            strict: false,
+            lesson: None,
         }
     }
 
     fn gen_demo(&mut self) {
         self.next_key = 0;
-        let mut notes = vec![];
-        for (index, pitch) in (-20..5).enumerate() {
-            notes.push(TrainNote {
-                index,
-                pitch,
-                pressed_key: default(),
-            });
-        }
-        self.notes = notes;
+        self.notes = (-20..5)
+            .enumerate()
+            .map(|(index, pitch)| TrainNote::new(index, pitch))
+            .collect();
+    }
+
+    fn gen_course(&mut self, course: TrainCourse, num_notes: usize) {
+        let seed = self.rng.gen();
+        self.gen_course_seeded(course, num_notes, seed);
     }
 
-    fn gen_course(&mut self, course: TrainCourse) {
+    /// Generates the same notes for the same seed, so players can compete on
+    /// equal terms.
+    fn gen_course_seeded(&mut self, course: TrainCourse, num_notes: usize, seed: u64) {
         self.course = course;
+        self.lesson = None;
+        self.seed = seed;
         self.next_key = 0;
-        let mut rng = rand::thread_rng();
-        let mut notes = vec![];
-        for index in 0..16 {
-            notes.push(Self::_generate_course_note(index, course, &mut rng));
+        self.retry = false;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let generator = course.generator();
+        let mut pitches = vec![];
+        for _ in 0..num_notes.min(STAFF_MAX_NOTES) {
+            pitches.push(generator.next_pitch(&pitches, &mut rng));
         }
-        assert!(notes.len() <= STAFF_MAX_NOTES);
-        self.notes = notes;
+        self.notes = pitches
+            .into_iter()
+            .enumerate()
+            .map(|(index, pitch)| TrainNote::new(index, pitch))
+            .collect();
     }
 
-    fn _generate_course_note<R>(index: usize, course: TrainCourse, rng: &mut R) -> TrainNote
-    where
-        R: rand::Rng + ?Sized,
-    {
-        let pitch: i32 = match course {
-            TrainCourse::TrebleLines => *[0, 2, 4, 6, 8, 10, 12].choose(rng).unwrap(),
-            TrainCourse::TrebleSpaces => *[1, 3, 5, 7, 9, 11].choose(rng).unwrap(),
-            TrainCourse::TrebleAll => rng.gen_range(0..13),
-            TrainCourse::BassLines => -*[0, 2, 4, 6, 8, 10, 12].choose(rng).unwrap(),
-            TrainCourse::BassSpaces => -*[1, 3, 5, 7, 9, 11].choose(rng).unwrap(),
-            TrainCourse::BassAll => -rng.gen_range(0..13),
-            TrainCourse::All => -rng.gen_range(-12..13),
-        };
-        TrainNote {
-            index,
-            pitch,
-            pressed_key: default(),
+    fn gen_lesson(&mut self, lesson: usize, num_notes: usize) {
+        let seed = self.rng.gen();
+        self.lesson = Some(lesson);
+        self.seed = seed;
+        self.next_key = 0;
+        self.retry = false;
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let lesson = &curriculum::LESSONS[lesson];
+        self.notes = (0..num_notes.min(STAFF_MAX_NOTES))
+            .map(|index| TrainNote::new(index, lesson.random_pitch(&mut rng)))
+            .collect();
+    }
+
+    /// Starts the next round of the current lesson or course.
+    fn gen_next(&mut self, num_notes: usize) {
+        match self.lesson {
+            Some(lesson) => self.gen_lesson(lesson, num_notes),
+            None => self.gen_course(self.course, num_notes),
         }
     }
 
-    fn get_note_space(&self) -> f32 {
-        let segments_f = 1.0f32.max(self.notes.len() as f32 - 1.0);
-        (STAFF_WIDTH - STAFF_NOTE_X - 40) as f32 / segments_f
+    /// Lessons name the notes of the staff, courses judge their own.
+    fn judge(&self, pitch: i32, answer: &Answer) -> bool {
+        match self.lesson {
+            Some(_) => answer.names(pitch),
+            None => self.course.generator().is_right(pitch, answer),
+        }
     }
-}
 
-fn staff_update(mut gizmos: Gizmos, train: ResMut<TrainSequence>) {
-    // Draw staff
-    for idx in 0..5 {
-        let x = STAFF_X as f32;
-        let y = (STAFF_Y + STAFF_SPACE_Y + idx * STAFF_SPACE_Y) as f32;
-        gizmos.line_2d(
-            Vec2::new(x, y),
-            Vec2::new(x + STAFF_WIDTH as f32, y),
-            Color::BLACK,
-        )
+    /// Answers the note under the cursor with the key of a pitch class.
+    fn answer(&mut self, key: usize) -> Option<NoteAnswered> {
+        let index = self.next_key;
+        let pitch = self.notes.get(index)?.pitch;
+        let right = self.judge(pitch, &Answer { key, placed: None });
+        let note = &mut self.notes[index];
+        note.pressed_key = Some(key);
+        note.attempts += 1;
+        note.right = right;
+        let correct = note.is_right();
+        if correct || !self.strict {
+            self.next_key += 1;
+        }
+        Some(NoteAnswered {
+            index,
+            pitch: note.pitch,
+            pressed_key: key,
+            correct,
+        })
     }
 
-    for idx in 0..5 {
-        let x = STAFF_X as f32;
-        let y = (STAFF_Y - STAFF_SPACE_Y - idx * STAFF_SPACE_Y) as f32;
-        gizmos.line_2d(
-            Vec2::new(x, y),
-            Vec2::new(x + STAFF_WIDTH as f32, y),
-            Color::BLACK,
-        )
+    /// Answers the note under the cursor by writing `pitch` on the staff. Unless
+    /// the octave was asked for, any octave of the named note is right.
+    fn place(&mut self, pitch: i32) -> Option<NoteAnswered> {
+        let mode = self.mode;
+        let index = self.next_key;
+        let mut shown = self.notes.get(index)?.pitch;
+        if mode == DrillMode::Write && pitch_class(pitch) == pitch_class(shown) {
+            shown = pitch;
+        }
+        let answer = Answer {
+            key: pitch_class(pitch),
+            placed: Some(pitch),
+        };
+        let right = self.judge(shown, &answer);
+        let note = &mut self.notes[index];
+        note.pitch = shown;
+        note.placed = Some(pitch);
+        note.pressed_key = Some(pitch_class(pitch));
+        note.attempts += 1;
+        note.right = right;
+        let correct = note.is_right();
+        if correct || !self.strict {
+            self.next_key += 1;
+        }
+        Some(NoteAnswered {
+            index,
+            pitch: note.pitch,
+            pressed_key: pitch_class(pitch),
+            correct,
+        })
     }
 
-    // Draw extra lines
-    let note_space = train.get_note_space();
+    /// Where the note head is drawn, the write modes only show what the
+    /// student wrote.
+    fn shown_pitch(&self, note: &TrainNote) -> Option<i32> {
+        match self.mode {
+            DrillMode::Read => Some(note.pitch),
+            DrillMode::Write | DrillMode::WriteWithOctave => note.placed,
+            DrillMode::Explore => None,
+        }
+    }
+
+    /// Builds a round out of the notes answered wrong so far, padded with their
+    /// neighbouring pitches when there are only a few, as far as ledger lines
+    /// reach. Returns false when there is nothing to retry.
+    fn gen_retry(&mut self) -> bool {
+        let mistakes: Vec<i32> = self.notes[..self.next_key]
+            .iter()
+            .filter(|n| !n.is_correct())
+            .map(|n| n.pitch)
+            .collect();
+        if mistakes.is_empty() {
+            return false;
+        }
 
-    fn draw_extra_line(g: &mut Gizmos, x: f32, width: f32, pitch: i32) {
-        let y = pitch_to_y(pitch);
-        let hw = width * 0.5;
-        g.line_2d(Vec2::new(x - hw, y), Vec2::new(x + hw, y), Color::BLACK);
+        let mut pitches = mistakes.clone();
+        let mut offset: i32 = 1;
+        let span = pointer::POINTER_PITCHES.end() - pointer::POINTER_PITCHES.start();
+        while pitches.len() < RETRY_MIN_NOTES && offset.abs() <= span {
+            pitches.extend(
+                mistakes
+                    .iter()
+                    .map(|pitch| pitch + offset)
+                    .filter(|pitch| pointer::POINTER_PITCHES.contains(pitch)),
+            );
+            // 1, -1, 2, -2, ...
+            offset = if offset > 0 { -offset } else { -offset + 1 };
+        }
+        pitches.truncate(RETRY_MIN_NOTES.max(mistakes.len()).min(STAFF_MAX_NOTES));
+        pitches.shuffle(&mut self.rng);
+
+        self.next_key = 0;
+        self.retry = true;
+        self.notes = pitches
+            .into_iter()
+            .enumerate()
+            .map(|(index, pitch)| TrainNote::new(index, pitch))
+            .collect();
+        true
     }
+}
 
-    for note in train.notes.iter() {
-        let x = index_to_x(note.index, note_space);
-        if note.pitch == 0 {
-            draw_extra_line(&mut gizmos, x, note_space, 0);
-        } else if note.pitch.abs() > 10 {
-            let mut pitch: i32 = 12;
-            while pitch <= note.pitch {
-                draw_extra_line(&mut gizmos, x, note_space, pitch);
-                pitch += 2;
+/// Draws the cursor around the note waiting for an answer, the staff itself is
+/// drawn by the engraving meshes.
+fn staff_update(
+    mut gizmos: Gizmos<TrainerGizmos>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+) {
+    let note_space = layout.note_space(train.notes.len());
+
+    // Draw Rectangle
+    let next_key = train.next_key;
+    if next_key < train.notes.len() {
+        let x = layout.index_to_x(next_key, note_space);
+        let color = settings.theme.palette().cursor;
+        match train.mode {
+            DrillMode::Read => {
+                let y = layout.pitch_to_y(train.notes[next_key].pitch);
+                gizmos.rect_2d(Vec2::new(x, y), 0., Vec2::splat(34. * layout.scale), color);
             }
-            let mut pitch: i32 = -12;
-            while pitch >= note.pitch {
-                draw_extra_line(&mut gizmos, x, note_space, pitch);
-                pitch -= 2;
+            // The whole column, the note goes anywhere on the grand staff
+            DrillMode::Write | DrillMode::WriteWithOctave => {
+                let (top, bottom) = (layout.pitch_to_y(13), layout.pitch_to_y(-13));
+                gizmos.rect_2d(
+                    Vec2::new(x, (top + bottom) * 0.5),
+                    0.,
+                    Vec2::new(40. * layout.scale, top - bottom),
+                    color,
+                );
             }
+            // The ghost note at the pointer is the only note
+            DrillMode::Explore => {}
         }
     }
+}
 
-    // Draw Rectangle
+/// Names the note to write above the staff.
+fn staff_update_prompt(
+    mut prompts: Query<(&mut Text, &mut Transform, &mut Visibility), With<PromptText>>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = prompts.get_single_mut() else {
+        return;
+    };
     let next_key = train.next_key;
-    if next_key < train.notes.len() {
-        let x = index_to_x(next_key, note_space);
-        let y = pitch_to_y(train.notes[next_key].pitch);
-        gizmos.rect_2d(Vec2::new(x, y), 0., Vec2::splat(34.), Color::srgb(0., 0., 1.));
+    let writing = matches!(train.mode, DrillMode::Write | DrillMode::WriteWithOctave);
+    if !writing || next_key >= train.notes.len() {
+        *visibility = Visibility::Hidden;
+        return;
+    }
+    *visibility = Visibility::Visible;
+    let pitch = train.notes[next_key].pitch;
+    let section = &mut text.sections[0];
+    section.value = match train.mode {
+        DrillMode::WriteWithOctave => settings.naming.name_with_octave(pitch, name_font.loaded),
+        _ => settings.naming.name(pitch, name_font.loaded).to_string(),
+    };
+    section.style.color = settings.theme.palette().cursor;
+    section.style.font_size = 36. * layout.scale;
+    let x = layout.index_to_x(next_key, layout.note_space(train.notes.len()));
+    *transform = Transform::from_xyz(x, layout.pitch_to_y(16), 1.);
+}
+
+fn staff_update_clefs(mut clefs: Query<(&ClefSprite, &mut Transform)>, layout: Res<StaffLayout>) {
+    if !layout.is_changed() {
+        return;
+    }
+    const CLEF_X: f32 = 40.;
+    for (clef, mut transform) in &mut clefs {
+        let (ref offset, _) = STAFF_ELEMENTS[clef.element];
+        let x = layout.staff_x + (CLEF_X + offset.x) * layout.scale;
+        let y = layout.pitch_to_y(clef.pitch) + offset.y * layout.scale;
+        *transform = Transform::from_xyz(x, y, 0.5).with_scale(Vec3::splat(layout.scale));
     }
 }
 
 fn staff_update_sprites(
     mut sprites: Query<(&Sprite, &NoteSpriteInfo, &mut Transform, &mut Visibility)>,
     train: ResMut<TrainSequence>,
+    layout: Res<StaffLayout>,
 ) {
     let num_notes = train.notes.len();
-    let note_space = train.get_note_space();
+    let note_space = layout.note_space(num_notes);
     for (_, info, mut transform, mut visibility) in &mut sprites {
         let index = info.index;
-        if index < num_notes {
+        let shown = train
+            .notes
+            .get(index)
+            .and_then(|note| train.shown_pitch(note));
+        if let Some(pitch) = shown {
             *visibility = Visibility::Visible;
 
-            let note = &train.notes[index];
-
-            let x = index_to_x(note.index, note_space);
-            let y = pitch_to_y(note.pitch);
+            let x = layout.index_to_x(index, note_space);
+            let y = layout.pitch_to_y(pitch);
 
-            *transform = Transform::from_xyz(x, y, 0.);
+            *transform = Transform::from_xyz(x, y, 0.5).with_scale(Vec3::splat(layout.scale));
         } else {
             *visibility = Visibility::Hidden;
         }
     }
 }
 
+/// Number of answered notes showing their labels.
+fn labelled_notes(train: &TrainSequence, feedback: FeedbackStyle) -> usize {
+    match feedback {
+        FeedbackStyle::EndOfRound if train.next_key < train.notes.len() => 0,
+        _ => train.next_key,
+    }
+}
+
 fn staff_update_labels(
     mut labels: Query<(&mut Text, &NoteSpriteInfo, &mut Transform, &mut Visibility)>,
     train: ResMut<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
 ) {
-    let num_visible_labels = train.next_key;
-    let note_space = train.get_note_space();
+    let num_visible_labels = labelled_notes(&train, settings.feedback);
+    let note_space = layout.note_space(train.notes.len());
     for (mut text, info, mut transform, mut visibility) in &mut labels {
         let index = info.index;
         if index < num_visible_labels {
@@ -226,17 +594,29 @@ fn staff_update_labels(
 
             let note = &train.notes[index];
 
-            let x = index_to_x(note.index, note_space);
-            let y = pitch_to_y(note.pitch);
+            let x = layout.index_to_x(note.index, note_space);
+            let y = layout.pitch_to_y(train.shown_pitch(note).unwrap_or(note.pitch));
 
-            let expected_key = pitch_to_char(note.pitch);
-            text.sections[0].value = expected_key.to_string();
-            let col = if expected_key == note.pressed_key {
-                Color::srgb(0., 1., 0.)
+            let palette = settings.theme.palette();
+            let (col, mark) = if note.is_correct() {
+                (palette.correct, "")
             } else {
-                Color::srgb(1., 0., 0.)
+                (palette.wrong, WRONG_MARK)
             };
-            text.sections[0].style.color = col;
+            text.sections[0].value = match train.mode {
+                DrillMode::WriteWithOctave => settings
+                    .naming
+                    .name_with_octave(note.pitch, name_font.loaded),
+                _ => settings
+                    .naming
+                    .name(note.pitch, name_font.loaded)
+                    .to_string(),
+            };
+            text.sections[1].value = mark.to_string();
+            for section in text.sections.iter_mut() {
+                section.style.color = col;
+                section.style.font_size = 30. * layout.scale;
+            }
 
             *transform = Transform::from_xyz(x, y, 1.);
         } else {
@@ -248,34 +628,67 @@ fn staff_update_labels(
 fn staff_setup(
     mut commands: Commands,
     mut train: ResMut<TrainSequence>,
-    asset_server: Res<AssetServer>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
 ) {
+    let layers = config.layers();
     {
-        const CLEF_X: i32 = 40;
-        // Treble Clef
-        let (ref offset, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_TREBLE];
-        let x = (STAFF_X + CLEF_X) as f32 + offset.x;
-        let y = (STAFF_Y + STAFF_SPACE_Y * 2) as f32 + offset.y;
-        commands.spawn(SpriteBundle {
-            texture: asset_server.load(asset_path),
-            transform: Transform::from_xyz(x, y, 0.),
-            ..default()
-        });
+        // Treble Clef, placed by staff_update_clefs
+        let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_TREBLE];
+        commands.spawn((
+            SpriteBundle {
+                texture: assets.load(asset_path),
+                ..default()
+            },
+            ClefSprite {
+                element: STAFF_ELEM_IDX_TREBLE,
+                pitch: 4,
+            },
+            Inked,
+            layers.clone(),
+        ));
 
-        let (ref offset, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_BASS];
-        let x = (STAFF_X + CLEF_X) as f32 + offset.x;
-        let y = (STAFF_Y - STAFF_SPACE_Y * 2) as f32 + offset.y;
-        commands.spawn(SpriteBundle {
-            texture: asset_server.load(asset_path),
-            transform: Transform::from_xyz(x, y, 0.),
-            ..default()
-        });
+        let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_BASS];
+        commands.spawn((
+            SpriteBundle {
+                texture: assets.load(asset_path),
+                ..default()
+            },
+            ClefSprite {
+                element: STAFF_ELEM_IDX_BASS,
+                pitch: -4,
+            },
+            Inked,
+            layers.clone(),
+        ));
     }
 
     train.gen_demo();
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: assets.font(),
+                    font_size: 36.0,
+                    ..default()
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        PromptText,
+        NameLabel,
+        layers.clone(),
+    ));
     {
         let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_NOTE_4];
-        let texture = asset_server.load(asset_path);
+        let texture = assets.load(asset_path);
+        let label_style = TextStyle {
+            font: assets.font(),
+            font_size: 30.0,
+            color: Color::srgb(0.9, 0.9, 0.9),
+        };
         for index in 0..STAFF_MAX_NOTES {
             commands.spawn((
                 SpriteBundle {
@@ -284,137 +697,301 @@ fn staff_setup(
                     ..default()
                 },
                 NoteSpriteInfo { index },
+                Inked,
+                layers.clone(),
             ));
             commands.spawn((
                 Text2dBundle {
                     text: Text {
-                        sections: vec![TextSection::new(
-                            "AAA",
-                            TextStyle {
-                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                font_size: 30.0,
-                                color: Color::srgb(0.9, 0.9, 0.9),
-                            },
-                        )],
+                        sections: vec![
+                            TextSection::new("AAA", label_style.clone()),
+                            // Mark of a wrong answer
+                            TextSection::new("", label_style.clone()),
+                        ],
                         ..default()
                     },
                     ..default()
                 },
                 NoteSpriteInfo { index },
+                NameLabel,
+                layers.clone(),
             ));
         }
     }
 }
 
-const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
-const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
-
 type ButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
     'world,
     'state,
-    (&'a Interaction, &'b mut UiImage, &'c mut BorderColor, &'d Children),
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d GameButton,
+    ),
     (Changed<Interaction>, With<Button>),
 >;
 
+/// Paints the game buttons and turns presses into `GameAction`s.
 fn game_button_system(
     mut interaction_query: ButtonQuery,
-    text_query: Query<&mut Text>,
+    settings: Res<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut actions: EventWriter<GameAction>,
+) {
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction == Interaction::Pressed {
+            // Keyboard presses go through the focused button
+            let source = if keys.any_just_pressed(accessibility::ACTIVATE_KEYS) {
+                InputSource::Keyboard
+            } else {
+                InputSource::Pointer
+            };
+            actions.send(GameAction {
+                button: *button,
+                source,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn game_action_system(
+    mut commands: Commands,
+    mut actions: EventReader<GameAction>,
     mut train: ResMut<TrainSequence>,
+    mut stats: ResMut<TrainStats>,
+    settings: Res<Settings>,
+    mut answered: EventWriter<NoteAnswered>,
+    mut completed: EventWriter<RoundCompleted>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    paused: Option<Res<embed::Paused>>,
 ) {
-    for (interaction, mut image, mut border_color, children) in &mut interaction_query {
-        let text = text_query.get(children[0]).unwrap();
-        match *interaction {
-            Interaction::Pressed => {
-                image.color = PRESSED_BUTTON;
-                border_color.0 = Color::WHITE;
-                let t = &text.sections[0].value;
-                if t.len() == 1 {
-                    let key_char = t.chars().next().unwrap();
-                    let next_key = train.next_key;
-                    if next_key < train.notes.len() {
-                        train.notes[next_key].pressed_key = key_char;
-                        train.next_key += 1;
-                    } else {
-                        let course = train.course;
-                        println!("GameOver, new round {}", course.as_ref());
-                        train.gen_course(course);
+    if paused.is_some() {
+        actions.clear();
+        return;
+    }
+    for action in actions.read() {
+        match action.button {
+            GameButton::Answer(_) | GameButton::Place(_) => {
+                let answer = match (action.button, train.mode) {
+                    (GameButton::Answer(key), DrillMode::Read) => train.answer(key),
+                    (GameButton::Place(pitch), DrillMode::Write | DrillMode::WriteWithOctave) => {
+                        train.place(pitch)
                     }
-                } else {
-                    match TrainCourse::from_str(t) {
-                        Ok(course) => train.gen_course(course),
-                        Err(err) => println!("{}", err),
+                    // Input of the other mode, like gamepad answers while writing
+                    _ => continue,
+                };
+                if let Some(answer) = answer {
+                    answered.send(answer);
+                    if train.next_key == train.notes.len() {
+                        stats.record(&train);
+                        completed.send(RoundCompleted::new(&train, settings.naming));
+                        println!(
+                            "Round finished, accuracy {:.0}% (retry {:.0}%)",
+                            stats.course.accuracy() * 100.,
+                            stats.retry.accuracy() * 100.
+                        );
                     }
+                } else if train.retry && train.gen_retry() {
+                    // Keep drilling until every mistake is answered correctly
+                    println!("GameOver, retry mistakes");
+                } else {
+                    println!("GameOver, new round {}", train.course.as_ref());
+                    train.gen_next(settings.notes_per_round);
+                }
+            }
+            GameButton::Retry => {
+                commands.remove_resource::<Versus>();
+                if !train.gen_retry() {
+                    println!("No mistakes to retry");
                 }
             }
-            Interaction::Hovered => {
-                image.color = HOVERED_BUTTON;
-                border_color.0 = Color::BLACK;
+            GameButton::Course(course) => {
+                commands.remove_resource::<Versus>();
+                train.gen_course(course, settings.notes_per_round);
             }
-            Interaction::None => {
-                image.color = NORMAL_BUTTON;
-                border_color.0 = Color::BLACK;
+            GameButton::Settings => next_screen.set(Screen::Settings),
+            GameButton::Lessons => next_screen.set(Screen::Lessons),
+            GameButton::Profiles => next_screen.set(Screen::Profiles),
+            GameButton::Homework => next_screen.set(Screen::Homework),
+            GameButton::Seeded {
+                course,
+                notes,
+                seed,
+            } => {
+                commands.remove_resource::<Versus>();
+                train.gen_course_seeded(course, notes, seed);
+            }
+            GameButton::Lesson(lesson) => {
+                commands.remove_resource::<Versus>();
+                train.gen_lesson(lesson, settings.notes_per_round);
+            }
+            GameButton::Versus(mode) => {
+                let seed = train.rng.gen();
+                let versus = Versus::new(
+                    mode,
+                    train.course,
+                    settings.notes_per_round,
+                    seed,
+                    train.mode,
+                );
+                *train = versus.players[0].train.clone();
+                commands.insert_resource(versus);
+                if mode == VersusMode::Race {
+                    next_screen.set(Screen::Race);
+                }
             }
         }
     }
 }
 
-fn game_button_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Courses
-    commands
-        .spawn(NodeBundle {
+fn game_button_bundle(button: GameButton, palette: &Palette) -> (ButtonBundle, GameButton) {
+    (
+        ButtonBundle {
             style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
+                width: Val::Px(130.0),
+                height: Val::Px(65.0),
+                border: UiRect::all(Val::Px(1.0)),
                 flex_direction: FlexDirection::Column,
-                ..Default::default()
+                // horizontally center child text
+                justify_content: JustifyContent::Center,
+                // vertically center child text
+                align_items: AlignItems::Center,
+                ..default()
             },
-            ..Default::default()
+            border_color: BorderColor(palette.button_border),
+            border_radius: BorderRadius::all(Val::Px(4.)),
+            image: UiImage::default().with_color(palette.button),
+            ..default()
+        },
+        button,
+    )
+}
+
+fn game_button_label(label: &str, font: Handle<Font>, palette: &Palette) -> TextBundle {
+    TextBundle::from_section(
+        label,
+        TextStyle {
+            font,
+            font_size: 18.0,
+            color: palette.button_text,
+        },
+    )
+}
+
+/// Adds the buttons of the courses registered since the course bar was built,
+/// like course scripts done loading.
+fn course_bar_system(
+    mut commands: Commands,
+    mut generation: Local<Option<usize>>,
+    bars: Query<(Entity, &Children), With<CourseBar>>,
+    buttons: Query<&GameButton>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let current = courses::generation();
+    if *generation == Some(current) {
+        return;
+    }
+    let Ok((bar, children)) = bars.get_single() else {
+        return;
+    };
+    *generation = Some(current);
+    let shown: Vec<_> = buttons
+        .iter_many(children)
+        .filter_map(|button| match button {
+            GameButton::Course(course) => Some(*course),
+            _ => None,
         })
-        .with_children(|root| {
-            root.spawn(NodeBundle {
+        .collect();
+    let palette = settings.theme.palette();
+    let mut index = shown.len();
+    for course in config.courses() {
+        if shown.contains(&course) {
+            continue;
+        }
+        let button = commands
+            .spawn(game_button_bundle(GameButton::Course(course), palette))
+            .with_children(|parent| {
+                parent.spawn(game_button_label(course.as_ref(), assets.font(), palette));
+            })
+            .id();
+        commands.entity(bar).insert_children(index, &[button]);
+        index += 1;
+    }
+}
+
+fn game_button_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    let palette = settings.theme.palette();
+    // Courses
+    commands
+        .spawn((
+            NodeBundle {
                 style: Style {
-                    width: Val::Percent(100.0),
-                    height: Val::Percent(50.0),
-                    align_items: AlignItems::FlexStart,
+                    align_items: AlignItems::Center,
                     justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..config.region_style()
+                },
+                ..Default::default()
+            },
+            TrainerUi,
+            ScaledUi,
+        ))
+        .with_children(|root| {
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(50.0),
+                        align_items: AlignItems::FlexStart,
+                        align_content: AlignContent::FlexStart,
+                        justify_content: JustifyContent::Center,
+                        // Portrait screens fit fewer courses per row
+                        flex_wrap: FlexWrap::Wrap,
+                        ..default()
+                    },
                     ..default()
                 },
-                ..default()
-            })
+                CourseBar,
+            ))
             .with_children(|parent| {
                 #[allow(clippy::unused_enumerate_index)]
-                for (_idx, var) in TrainCourse::iter().enumerate() {
+                for (_idx, (button, var)) in config
+                    .courses()
+                    .into_iter()
+                    .map(|course| (GameButton::Course(course), course.as_ref().to_string()))
+                    .chain([
+                        (GameButton::Lessons, LESSONS_LABEL.to_string()),
+                        (GameButton::Homework, HOMEWORK_LABEL.to_string()),
+                        (GameButton::Retry, RETRY_LABEL.to_string()),
+                        (
+                            GameButton::Versus(VersusMode::HotSeat),
+                            HOT_SEAT_LABEL.to_string(),
+                        ),
+                        (GameButton::Versus(VersusMode::Race), RACE_LABEL.to_string()),
+                        (GameButton::Settings, SETTINGS_LABEL.to_string()),
+                        (GameButton::Profiles, PROFILES_LABEL.to_string()),
+                    ])
+                    .enumerate()
+                {
                     parent
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                width: Val::Px(150.0),
-                                height: Val::Px(65.0),
-                                border: UiRect::all(Val::Px(1.0)),
-                                flex_direction: FlexDirection::Column,
-                                // horizontally center child text
-                                justify_content: JustifyContent::Center,
-                                // vertically center child text
-                                align_items: AlignItems::Center,
-                                ..default()
-                            },
-                            border_color: BorderColor(Color::BLACK),
-                            border_radius: BorderRadius::all(Val::Px(4.)),
-                            image: UiImage::default().with_color(NORMAL_BUTTON),
-                            ..default()
-                        })
+                        .spawn(game_button_bundle(button, palette))
                         .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                var.as_ref(),
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 20.0,
-                                    color: Color::srgb(0.9, 0.9, 0.9),
-                                },
-                            ));
-                            if let TrainCourse::All = var {
+                            parent.spawn(game_button_label(&var, assets.font(), palette));
+                            if var == TrainCourse::ALL.as_ref() {
                                 // Resolution label
                                 parent.spawn((
                                     TextBundle::from_section(
@@ -433,44 +1010,59 @@ fn game_button_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
             });
 
             // Keyboard
-            root.spawn(NodeBundle {
-                style: Style {
-                    width: Val::Percent(100.0),
-                    height: Val::Percent(50.0),
-                    align_items: AlignItems::FlexEnd,
-                    justify_content: JustifyContent::Center,
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(50.0),
+                        align_items: AlignItems::FlexEnd,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
                     ..default()
                 },
-                ..default()
-            })
+                AnswerKeyboard,
+            ))
             .with_children(|parent| {
                 #[allow(clippy::unused_enumerate_index)]
-                for (_idx, &key) in PITCH_NAMES.iter().enumerate() {
+                for (idx, key) in settings
+                    .naming
+                    .names(name_font.loaded)
+                    .into_iter()
+                    .enumerate()
+                {
                     parent
-                        .spawn(ButtonBundle {
-                            style: Style {
-                                width: Val::Px(100.0),
-                                height: Val::Px(130.0),
-                                border: UiRect::all(Val::Px(1.0)),
-                                // horizontally center child text
-                                justify_content: JustifyContent::Center,
-                                // vertically center child text
-                                align_items: AlignItems::Center,
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(100.0),
+                                    height: Val::Px(130.0),
+                                    border: UiRect::all(Val::Px(1.0)),
+                                    // horizontally center child text
+                                    justify_content: JustifyContent::Center,
+                                    // vertically center child text
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                border_color: BorderColor(palette.button_border),
+                                border_radius: BorderRadius::all(Val::Px(4.)),
+                                image: UiImage::default().with_color(palette.button),
                                 ..default()
                             },
-                            border_color: BorderColor(Color::BLACK),
-                            border_radius: BorderRadius::all(Val::Px(4.)),
-                            image: UiImage::default().with_color(NORMAL_BUTTON),
-                            ..default()
-                        })
+                            AnswerKey,
+                            GameButton::Answer(idx),
+                        ))
                         .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                key,
-                                TextStyle {
-                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                                    font_size: 40.0,
-                                    color: Color::srgb(0.9, 0.9, 0.9),
-                                },
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    key,
+                                    TextStyle {
+                                        font: assets.font(),
+                                        font_size: 40.0,
+                                        color: palette.button_text,
+                                    },
+                                ),
+                                NameLabel,
                             ));
                         });
                 }
@@ -478,6 +1070,76 @@ fn game_button_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         });
 }
 
+/// Follows the strict feedback setting, also for the sequences swapped in by
+/// matches, and starts a new round when the drill mode changes, the notes of
+/// the current one are shown differently.
+fn drill_mode_system(
+    mut commands: Commands,
+    mut train: ResMut<TrainSequence>,
+    settings: Res<Settings>,
+) {
+    let strict = settings.feedback == FeedbackStyle::Strict;
+    if train.strict != strict {
+        train.strict = strict;
+    }
+    if !settings.is_changed() || settings.mode == train.mode {
+        return;
+    }
+    commands.remove_resource::<Versus>();
+    train.mode = settings.mode;
+    train.gen_next(settings.notes_per_round);
+}
+
+fn answer_keyboard_visibility(
+    train: Res<TrainSequence>,
+    mut keyboards: Query<&mut Visibility, With<AnswerKeyboard>>,
+) {
+    if !train.is_changed() {
+        return;
+    }
+    let visibility = match train.mode {
+        DrillMode::Read => Visibility::Inherited,
+        DrillMode::Write | DrillMode::WriteWithOctave | DrillMode::Explore => Visibility::Hidden,
+    };
+    for mut keyboard in &mut keyboards {
+        keyboard.set_if_neq(visibility);
+    }
+}
+
+/// Hides the trainer buttons behind other screens, so they can't be focused.
+fn trainer_ui_visibility(
+    screen: Res<State<Screen>>,
+    mut roots: Query<&mut Visibility, With<TrainerUi>>,
+) {
+    let visibility = match screen.get() {
+        Screen::Trainer => Visibility::Inherited,
+        _ => Visibility::Hidden,
+    };
+    for mut root in &mut roots {
+        *root = visibility;
+    }
+}
+
+/// Renames the answer keys after switching naming systems.
+fn naming_update_buttons(
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+    buttons: Query<(&GameButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (button, children) in &buttons {
+        let GameButton::Answer(key) = *button else {
+            continue;
+        };
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].value = settings.naming.names(name_font.loaded)[key].to_string();
+        }
+    }
+}
+
 /// This system shows how to respond to a window being resized.
 /// Whenever the window is resized, the text will update with the new resolution.
 fn on_resize_system(
@@ -485,7 +1147,10 @@ fn on_resize_system(
     mut resize_reader: EventReader<WindowResized>,
     // mut windows: Query<&mut Window>,
 ) {
-    let mut text = q.single_mut();
+    // The label is on the All course button, which may be left out
+    let Ok(mut text) = q.get_single_mut() else {
+        return;
+    };
     for e in resize_reader.read() {
         // let mut window = windows.single_mut();
         // window.resolution.set(1200., 540.);
@@ -505,29 +1170,308 @@ fn on_resize_system(
 //     }
 // }
 
-fn setup(mut commands: Commands, _asset_server: Res<AssetServer>) {
-    commands.spawn(Camera2dBundle::default());
-    // for (idx, &(ref offset, asset_path)) in STAFF_ELEMENTS.iter().enumerate() {
-    //     let x = idx as f32 * 100.;
-    //     commands.spawn(SpriteBundle {
-    //         texture: asset_server.load(asset_path),
-    //         transform: Transform::from_xyz(x + offset.x, offset.y, 0.),
-    //         ..default()
-    //     });
-    // }
+/// The staff trainer, by default on the whole window with its own camera.
+/// Games hosting it as a mini-game give it a region of their window and
+/// listen to `NoteAnswered` and `RoundCompleted`:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// use staff_trainer::{TrainCourse, TrainerPlugin};
+///
+/// App::new().add_plugins(DefaultPlugins).add_plugins(TrainerPlugin {
+///     courses: vec![TrainCourse::TREBLE_LINES, TrainCourse::TREBLE_ALL],
+///     region: Rect::new(0.5, 0., 1., 1.),
+///     asset_root: "staff_trainer".to_string(),
+///     ..default()
+/// });
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrainerPlugin {
+    /// Courses on the course bar, in order, the first one starts. Every
+    /// registered course when empty
+    pub courses: Vec<TrainCourse>,
+    /// Part of the window the trainer covers, as fractions of its width and
+    /// height from the top left corner
+    pub region: Rect,
+    /// Render layer of the staff, so the host's cameras can leave it out
+    pub render_layer: usize,
+    /// Spawns a 2d camera centered on the window, otherwise the host puts
+    /// `TrainerCamera` on one of its cameras
+    pub spawn_camera: bool,
+    /// Folder of the trainer's images and fonts among the host's assets
+    pub asset_root: String,
+    /// Course scripts in the asset folder, their courses join the course bar
+    /// once loaded
+    pub course_scripts: Vec<String>,
 }
 
-pub struct TrainerPlugin;
+impl Default for TrainerPlugin {
+    fn default() -> Self {
+        TrainerPlugin {
+            courses: Vec::new(),
+            region: Rect::new(0., 0., 1., 1.),
+            render_layer: 0,
+            spawn_camera: true,
+            asset_root: String::new(),
+            course_scripts: Vec::new(),
+        }
+    }
+}
 
 impl Plugin for TrainerPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(TrainSequence::new())
-            .add_systems(Startup, (setup, staff_setup, game_button_setup))
+        let session_seed = match app.world().get_resource::<Replay>() {
+            Some(replay) => replay.seed(),
+            None => {
+                let seed = rand::random();
+                app.insert_resource(Recorder::new(seed));
+                seed
+            }
+        };
+        let config = TrainerConfig::from(self);
+        let mut train = TrainSequence::new(session_seed);
+        let courses = config.courses();
+        if let Some(&first) = courses.first() {
+            if !courses.contains(&train.course) {
+                train.course = first;
+            }
+        }
+        if self.spawn_camera {
+            app.add_systems(Startup, config::camera_setup);
+        }
+        app.insert_gizmo_config(
+            TrainerGizmos,
+            GizmoConfig {
+                render_layers: config.layers(),
+                ..default()
+            },
+        );
+        app.insert_resource(config)
+            .insert_resource(train)
+            .init_resource::<StaffLayout>()
+            .init_resource::<TrainStats>()
+            .init_resource::<pointer::StaffPointer>()
+            .init_resource::<NameFont>()
+            .init_resource::<curriculum::Curriculum>()
+            .init_resource::<curriculum::Progress>()
+            .insert_resource(profiles::Profiles::load())
+            .init_resource::<profiles::ProfileEditor>()
+            .init_resource::<homework::Homework>()
+            .init_resource::<homework::HomeworkRound>()
+            .init_resource::<homework::HomeworkMessage>()
+            // Loaded with the profile
+            .init_resource::<Settings>()
+            .init_state::<Screen>()
+            .init_asset::<scripted::CourseScript>()
+            .init_asset_loader::<scripted::CourseScriptLoader>()
+            .add_event::<NoteAnswered>()
+            .add_event::<GameAction>()
+            .add_event::<RoundCompleted>()
+            .add_event::<HostCommand>()
+            .add_systems(
+                Startup,
+                (
+                    layout::layout_setup,
+                    staff_setup,
+                    engraving::engraving_setup,
+                    game_button_setup,
+                    scripted::course_script_setup,
+                    accessibility::accessibility_setup,
+                    pointer::ghost_note_setup,
+                    profiles::profile_startup,
+                    mistakes::mistake_ghost_setup,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    layout::layout_resize_system,
+                    layout::layout_apply_ui,
+                    staff_update_clefs,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                layout::ui_scale_system.before(bevy::ui::UiSystem::Layout),
+            )
+            .add_systems(
+                Update,
+                (
+                    staff_update_sprites,
+                    staff_update.run_if(not(in_state(Screen::Race))),
+                    staff_update_labels,
+                    staff_update_prompt,
+                    answer_keyboard_visibility,
+                    pointer::ghost_note_system,
+                    pointer::ghost_label_system,
+                    mistakes::mistake_ghost_system,
+                    flash::flash_spawn_system,
+                    flash::flash_update_system,
+                    mistakes::mistake_steps_system,
+                    pointer::explore_sound_system,
+                    engraving::engraving_update,
+                )
+                    .after(layout::layout_resize_system)
+                    .after(TrainInput),
+            )
+            .add_systems(
+                Update,
+                (
+                    on_resize_system,
+                    scripted::course_script_system,
+                    course_bar_system,
+                ),
+            )
             .add_systems(
                 Update,
-                (staff_update_sprites, staff_update, staff_update_labels),
+                pointer::pointer_system
+                    .after(layout::layout_resize_system)
+                    .before(TrainInput),
+            )
+            .add_systems(
+                Update,
+                (
+                    drill_mode_system,
+                    (
+                        game_button_system,
+                        gamepad::gamepad_system,
+                        pointer::pointer_place_system,
+                    )
+                        .run_if(not(resource_exists::<Replay>)),
+                    recording::replay_system.run_if(resource_exists::<Replay>),
+                    game_action_system,
+                    naming_update_buttons,
+                    versus::versus_turn_system.run_if(resource_exists::<Versus>),
+                )
+                    .chain()
+                    .in_set(TrainInput)
+                    .run_if(in_state(Screen::Trainer)),
+            )
+            .add_systems(
+                Update,
+                (
+                    embed::host_command_system,
+                    embed::launch_system.run_if(
+                        resource_exists::<LaunchOptions>
+                            .and_then(resource_exists::<profiles::Profile>)
+                            .and_then(in_state(Screen::Trainer)),
+                    ),
+                )
+                    .run_if(not(resource_exists::<Replay>))
+                    .before(TrainInput),
+            )
+            .add_systems(
+                Update,
+                (
+                    settings::settings_apply,
+                    naming::name_font_system,
+                    theme::theme_prepare_textures,
+                    theme::theme_apply,
+                    sound::sound_answer_system,
+                    recording::recording_system
+                        .run_if(resource_exists::<Recorder>)
+                        .after(TrainInput),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    accessibility::focus_navigation_system.run_if(profiles::not_typing),
+                    accessibility::focus_ring_system,
+                    accessibility::accessibility_label_system,
+                    accessibility::accessibility_status_system,
+                    trainer_ui_visibility.run_if(state_changed::<Screen>),
+                ),
+            )
+            .add_systems(OnEnter(Screen::Settings), settings::settings_screen_setup)
+            .add_systems(OnExit(Screen::Settings), settings::settings_screen_cleanup)
+            .add_systems(
+                Update,
+                settings::settings_button_system.run_if(in_state(Screen::Settings)),
+            )
+            .add_systems(OnEnter(Screen::Lessons), curriculum::lessons_screen_setup)
+            .add_systems(OnExit(Screen::Lessons), curriculum::lesson_screen_cleanup)
+            .add_systems(OnEnter(Screen::LessonIntro), curriculum::lesson_intro_setup)
+            .add_systems(
+                OnExit(Screen::LessonIntro),
+                curriculum::lesson_screen_cleanup,
+            )
+            .add_systems(
+                Update,
+                (
+                    curriculum::lesson_button_system
+                        .run_if(in_state(Screen::Lessons).or_else(in_state(Screen::LessonIntro))),
+                    curriculum::lesson_progress_system
+                        .run_if(not(resource_exists::<Replay>))
+                        .after(TrainInput),
+                    curriculum::lesson_banner_system.after(TrainInput),
+                ),
+            )
+            .add_systems(OnExit(Screen::Profiles), profiles::profile_screen_cleanup)
+            .add_systems(
+                Update,
+                (
+                    (
+                        profiles::profile_screen_system,
+                        profiles::profile_button_system,
+                        profiles::profile_name_input_system,
+                    )
+                        .run_if(in_state(Screen::Profiles)),
+                    profiles::profile_save_system
+                        .run_if(resource_exists::<profiles::Profile>)
+                        .after(curriculum::lesson_progress_system),
+                ),
+            )
+            .add_systems(OnExit(Screen::Homework), homework::homework_screen_cleanup)
+            .add_systems(
+                Update,
+                (
+                    (
+                        homework::homework_screen_system,
+                        homework::homework_button_system,
+                    )
+                        .run_if(in_state(Screen::Homework)),
+                    homework::homework_progress_system
+                        .run_if(not(resource_exists::<Replay>))
+                        .after(TrainInput)
+                        .before(profiles::profile_save_system),
+                ),
+            )
+            .add_systems(OnEnter(Screen::Race), versus::race_screen_setup)
+            .add_systems(OnExit(Screen::Race), versus::versus_screen_cleanup)
+            .add_systems(OnEnter(Screen::Winner), versus::winner_screen_setup)
+            .add_systems(OnExit(Screen::Winner), versus::versus_screen_cleanup)
+            .add_systems(
+                Update,
+                (
+                    versus::versus_button_system
+                        .run_if(in_state(Screen::Race).or_else(in_state(Screen::Winner))),
+                    versus::versus_cursor_system
+                        .run_if(in_state(Screen::Race).and_then(resource_exists::<Versus>)),
+                    versus::versus_banner_system,
+                    gamepad::gamepad_overlay_system,
+                ),
+            );
+        app.add_systems(Update, homework::homework_import_system);
+        #[cfg(target_os = "android")]
+        app.add_systems(
+            Update,
+            homework::opened_assignment_system.before(homework::homework_import_system),
+        );
+        if let Some(lrs) = xapi::Lrs::load() {
+            app.insert_resource(lrs);
+        }
+        app.add_systems(
+            Update,
+            (
+                xapi::xapi_statement_system
+                    .run_if(not(resource_exists::<Replay>))
+                    .after(curriculum::lesson_progress_system),
+                xapi::xapi_send_system,
             )
-            .add_systems(Update, on_resize_system)
-            .add_systems(Update, game_button_system);
+                .chain()
+                .run_if(resource_exists::<xapi::Lrs>),
+        );
     }
 }
@@ -0,0 +1,291 @@
+use bevy::{
+    a11y::{
+        accesskit::{Live, NodeBuilder, Role},
+        AccessibilityNode, Focus,
+    },
+    prelude::*,
+};
+
+use super::{
+    curriculum,
+    layout::TrainerNodes,
+    naming::NameFont,
+    settings::{DrillMode, Settings, SettingsButton},
+    versus::VersusMode,
+    GameButton, NoteAnswered, TrainSequence,
+};
+
+const FOCUS_RING_WIDTH: f32 = 3.;
+/// Keys pressing the focused button
+pub(super) const ACTIVATE_KEYS: [KeyCode; 3] =
+    [KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Space];
+
+/// Status line describing the note waiting for an answer.
+#[derive(Component)]
+pub(super) struct NoteStatus;
+
+/// Live region announcing the result of every answer.
+#[derive(Component)]
+pub(super) struct Announcer;
+
+/// Outline added to the focused button by `focus_ring_system`.
+#[derive(Component)]
+pub(super) struct FocusRing;
+
+/// Sets the accessible name, leaving unchanged nodes alone so AccessKit only
+/// receives real updates.
+fn set_accessible_name(
+    commands: &mut Commands,
+    entity: Entity,
+    node: Option<Mut<AccessibilityNode>>,
+    role: Role,
+    name: String,
+) {
+    if node.as_ref().and_then(|node| node.name()) == Some(name.as_str()) {
+        return;
+    }
+    match node {
+        Some(mut node) => {
+            node.set_role(role);
+            node.set_name(name);
+        }
+        None => {
+            let mut node = NodeBuilder::new(role);
+            node.set_name(name);
+            commands.entity(entity).insert(AccessibilityNode(node));
+        }
+    }
+}
+
+pub(super) fn accessibility_setup(mut commands: Commands) {
+    let mut status = NodeBuilder::new(Role::Status);
+    status.set_live(Live::Polite);
+    commands.spawn((AccessibilityNode(status), NoteStatus));
+
+    let mut announcer = NodeBuilder::new(Role::Status);
+    announcer.set_live(Live::Assertive);
+    commands.spawn((AccessibilityNode(announcer), Announcer));
+}
+
+/// Names the buttons after what they do, rather than after their text.
+pub(super) fn accessibility_label_system(
+    mut commands: Commands,
+    mut game_buttons: Query<
+        (Entity, &GameButton, Option<&mut AccessibilityNode>),
+        Without<SettingsButton>,
+    >,
+    mut settings_buttons: Query<
+        (Entity, &SettingsButton, Option<&mut AccessibilityNode>),
+        Without<GameButton>,
+    >,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    for (entity, button, node) in &mut game_buttons {
+        let name = match *button {
+            GameButton::Course(course) => match course.generator().description() {
+                "" => format!("Start course {}", course.as_ref()),
+                drills => format!("Start course {}, {}", course.as_ref(), drills),
+            },
+            GameButton::Retry => "Retry mistakes".to_string(),
+            GameButton::Settings => "Settings".to_string(),
+            GameButton::Lessons => "Lessons".to_string(),
+            GameButton::Profiles => "Switch profile".to_string(),
+            GameButton::Homework => "Homework".to_string(),
+            GameButton::Seeded { course, notes, .. } => {
+                format!("Start {}, {} notes", course.as_ref(), notes)
+            }
+            GameButton::Lesson(lesson) => {
+                format!("Start lesson {}", curriculum::LESSONS[lesson].name)
+            }
+            GameButton::Versus(VersusMode::HotSeat) => "Start hot-seat match".to_string(),
+            GameButton::Versus(VersusMode::Race) => "Start race".to_string(),
+            GameButton::Answer(key) => {
+                format!("Answer {}", settings.naming.names(name_font.loaded)[key])
+            }
+            GameButton::Place(pitch) => {
+                format!("Write {}", settings.naming.name(pitch, name_font.loaded))
+            }
+        };
+        set_accessible_name(&mut commands, entity, node, Role::Button, name);
+    }
+    for (entity, button, node) in &mut settings_buttons {
+        let name = button.label(&settings, name_font.loaded);
+        set_accessible_name(&mut commands, entity, node, Role::Button, name);
+    }
+}
+
+pub(super) fn accessibility_status_system(
+    mut status: Query<&mut AccessibilityNode, (With<NoteStatus>, Without<Announcer>)>,
+    mut announcer: Query<&mut AccessibilityNode, (With<Announcer>, Without<NoteStatus>)>,
+    mut answers: EventReader<NoteAnswered>,
+    train: Res<TrainSequence>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    if train.is_changed() {
+        let num_notes = train.notes.len();
+        let text = if train.mode == DrillMode::Explore {
+            "exploring, point at the staff to hear its notes".to_string()
+        } else if train.next_key < num_notes {
+            let pitch = train.notes[train.next_key].pitch;
+            let task = match train.mode {
+                DrillMode::Read => "awaiting answer".to_string(),
+                DrillMode::Write => {
+                    format!("write {}", settings.naming.name(pitch, name_font.loaded))
+                }
+                _ => format!(
+                    "write {}",
+                    settings.naming.name_with_octave(pitch, name_font.loaded)
+                ),
+            };
+            format!("note {} of {}, {}", train.next_key + 1, num_notes, task)
+        } else {
+            let correct = train.notes.iter().filter(|n| n.is_correct()).count();
+            format!("round finished, {} of {} correct", correct, num_notes)
+        };
+        for mut node in &mut status {
+            if node.name() != Some(text.as_str()) {
+                node.set_name(text.clone());
+            }
+        }
+    }
+
+    for answer in answers.read() {
+        let expected = settings.naming.name(answer.pitch, name_font.loaded);
+        let pressed = settings.naming.names(name_font.loaded)[answer.pressed_key];
+        let text = if answer.correct {
+            format!("correct, {}", expected)
+        } else if train.strict {
+            format!("incorrect, you answered {}, try again", pressed)
+        } else {
+            format!("incorrect, it was {}, you answered {}", expected, pressed)
+        };
+        for mut node in &mut announcer {
+            node.set_name(text.clone());
+        }
+    }
+}
+
+/// Orders buttons for Tab traversal, row by row from the top left.
+fn reading_order(a: Vec2, b: Vec2) -> std::cmp::Ordering {
+    let row = |p: Vec2| (p.y / 8.).round() as i32;
+    row(a)
+        .cmp(&row(b))
+        .then(a.x.partial_cmp(&b.x).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+/// The closest button in the direction of an arrow key, straying sideways
+/// costs more than going further.
+fn nearest_in_direction(from: Vec2, dir: Vec2, candidates: &[(Entity, Vec2)]) -> Option<Entity> {
+    candidates
+        .iter()
+        .filter_map(|&(entity, pos)| {
+            let delta = pos - from;
+            let along = delta.dot(dir);
+            if along <= 1. {
+                return None;
+            }
+            let across = (delta - dir * along).length();
+            Some((entity, along + across * 2.))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(entity, _)| entity)
+}
+
+/// Moves the keyboard focus between the trainer's visible buttons with Tab and
+/// the arrow keys, Enter or Space presses the focused button.
+pub(super) fn focus_navigation_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<Focus>,
+    trainer_nodes: TrainerNodes,
+    buttons: Query<(Entity, &GlobalTransform, &InheritedVisibility), With<Button>>,
+    mut interactions: Query<&mut Interaction>,
+) {
+    let mut candidates: Vec<(Entity, Vec2)> = trainer_nodes
+        .iter()
+        .filter_map(|entity| buttons.get(entity).ok())
+        .filter(|(_, _, visibility)| visibility.get())
+        .map(|(entity, transform, _)| (entity, transform.translation().truncate()))
+        .collect();
+    candidates.sort_by(|a, b| reading_order(a.1, b.1));
+    if candidates.is_empty() {
+        return;
+    }
+
+    let current = focus
+        .0
+        .and_then(|entity| candidates.iter().position(|&(e, _)| e == entity));
+
+    if let Some(index) = current {
+        let entity = candidates[index].0;
+        if keys.any_just_pressed(ACTIVATE_KEYS) {
+            if let Ok(mut interaction) = interactions.get_mut(entity) {
+                *interaction = Interaction::Pressed;
+            }
+        } else if keys.any_just_released(ACTIVATE_KEYS) {
+            if let Ok(mut interaction) = interactions.get_mut(entity) {
+                if *interaction == Interaction::Pressed {
+                    *interaction = Interaction::None;
+                }
+            }
+        }
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let next = if keys.just_pressed(KeyCode::Tab) {
+        let len = candidates.len();
+        let index = match (current, shift) {
+            (Some(index), false) => (index + 1) % len,
+            (Some(index), true) => (index + len - 1) % len,
+            (None, false) => 0,
+            (None, true) => len - 1,
+        };
+        Some(candidates[index].0)
+    } else {
+        // UI coordinates grow downwards
+        let dir = [
+            (KeyCode::ArrowLeft, Vec2::NEG_X),
+            (KeyCode::ArrowRight, Vec2::X),
+            (KeyCode::ArrowUp, Vec2::NEG_Y),
+            (KeyCode::ArrowDown, Vec2::Y),
+        ]
+        .into_iter()
+        .find(|(key, _)| keys.just_pressed(*key))
+        .map(|(_, dir)| dir);
+        match (dir, current) {
+            (Some(dir), Some(index)) => nearest_in_direction(candidates[index].1, dir, &candidates),
+            (Some(_), None) => Some(candidates[0].0),
+            (None, _) => None,
+        }
+    };
+
+    if let Some(entity) = next {
+        focus.0 = Some(entity);
+    }
+}
+
+pub(super) fn focus_ring_system(
+    mut commands: Commands,
+    focus: Res<Focus>,
+    rings: Query<Entity, With<FocusRing>>,
+    buttons: Query<(), With<Button>>,
+    settings: Res<Settings>,
+) {
+    if !focus.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for entity in &rings {
+        commands.entity(entity).remove::<(Outline, FocusRing)>();
+    }
+    if let Some(entity) = focus.0.filter(|&entity| buttons.contains(entity)) {
+        commands.entity(entity).insert((
+            Outline::new(
+                Val::Px(FOCUS_RING_WIDTH),
+                Val::Px(FOCUS_RING_WIDTH),
+                settings.theme.palette().cursor,
+            ),
+            FocusRing,
+        ));
+    }
+}
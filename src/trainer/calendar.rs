@@ -0,0 +1,47 @@
+//! Dates and times stamped on the files and statements sent to teachers.
+
+/// The current UTC time as `YYYY-MM-DDThh:mm:ssZ`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(super) fn timestamp() -> Option<String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    // Civil date of a day count, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (now / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds = now % 86400;
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    ))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) fn timestamp() -> Option<String> {
+    // `YYYY-MM-DDThh:mm:ss.sssZ`, without the milliseconds
+    let iso: String = js_sys::Date::new_0().to_iso_string().into();
+    Some(format!("{}Z", iso.get(..19)?))
+}
+
+/// Today's date as `YYYY-MM-DD`.
+pub(super) fn today() -> Option<String> {
+    Some(timestamp()?[..10].to_string())
+}
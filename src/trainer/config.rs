@@ -0,0 +1,127 @@
+//! Options of `TrainerPlugin` for the systems, and what a host app needs to
+//! share its window with the trainer: the camera marker, the render layer and
+//! the asset folder.
+
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    render::view::{Layer, RenderLayers},
+};
+
+use super::{TrainCourse, TrainerPlugin};
+
+const FONT_PATH: &str = "fonts/FiraSans-Bold.ttf";
+
+/// Camera drawing the staff, pointer positions are read through it. Hosts
+/// owning the camera put this on theirs, with the trainer's render layer.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct TrainerCamera;
+
+/// Staff lines and cursors, drawn on the trainer's render layer only.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub(super) struct TrainerGizmos;
+
+#[derive(Resource, Clone, Debug)]
+pub(super) struct TrainerConfig {
+    pub courses: Vec<TrainCourse>,
+    pub region: Rect,
+    pub layer: Layer,
+    pub asset_root: String,
+    pub course_scripts: Vec<String>,
+    /// Covers the whole window with its own camera, so the window's clear
+    /// color and mode follow the settings
+    pub owns_window: bool,
+}
+
+impl From<&TrainerPlugin> for TrainerConfig {
+    fn from(plugin: &TrainerPlugin) -> Self {
+        TrainerConfig {
+            courses: plugin.courses.clone(),
+            region: plugin.region,
+            layer: plugin.render_layer,
+            asset_root: plugin.asset_root.clone(),
+            course_scripts: plugin.course_scripts.clone(),
+            owns_window: plugin.spawn_camera && plugin.region == Rect::new(0., 0., 1., 1.),
+        }
+    }
+}
+
+impl Default for TrainerConfig {
+    fn default() -> Self {
+        TrainerConfig::from(&TrainerPlugin::default())
+    }
+}
+
+impl TrainerConfig {
+    /// Courses on the course bar.
+    pub fn courses(&self) -> Vec<TrainCourse> {
+        match self.courses.is_empty() {
+            true => TrainCourse::all(),
+            false => self.courses.clone(),
+        }
+    }
+
+    pub fn layers(&self) -> RenderLayers {
+        RenderLayers::layer(self.layer)
+    }
+
+    fn asset_path(&self, path: &str) -> String {
+        match self.asset_root.trim_end_matches('/') {
+            "" => path.to_string(),
+            root => format!("{}/{}", root, path),
+        }
+    }
+
+    /// Size and center of the region in world units, for a camera centered on
+    /// the window.
+    pub fn world_region(&self, window_size: Vec2) -> (Vec2, Vec2) {
+        let size = self.region.size() * window_size;
+        let center = (self.region.center() - Vec2::splat(0.5)) * window_size;
+        // UI coordinates grow downwards, world coordinates upwards
+        (size, Vec2::new(center.x, -center.y))
+    }
+
+    /// Root node style of the trainer screens, covering the region.
+    pub fn region_style(&self) -> Style {
+        Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(self.region.min.x * 100.),
+            top: Val::Percent(self.region.min.y * 100.),
+            width: Val::Percent(self.region.width() * 100.),
+            height: Val::Percent(self.region.height() * 100.),
+            ..default()
+        }
+    }
+
+    /// Style of a label in the bottom left corner of the region.
+    pub fn corner_style(&self) -> Style {
+        Style {
+            position_type: PositionType::Absolute,
+            left: Val::Percent(self.region.min.x * 100.),
+            bottom: Val::Percent((1. - self.region.max.y) * 100.),
+            margin: UiRect::all(Val::Px(12.)),
+            ..default()
+        }
+    }
+}
+
+/// Loads the trainer's images and fonts from its asset folder.
+#[derive(SystemParam)]
+pub(super) struct TrainerAssets<'w> {
+    server: Res<'w, AssetServer>,
+    config: Res<'w, TrainerConfig>,
+}
+
+impl TrainerAssets<'_> {
+    pub fn load<A: Asset>(&self, path: &str) -> Handle<A> {
+        self.server.load(self.config.asset_path(path))
+    }
+
+    pub fn font(&self) -> Handle<Font> {
+        self.load(FONT_PATH)
+    }
+}
+
+pub(super) fn camera_setup(mut commands: Commands, config: Res<TrainerConfig>) {
+    commands.spawn((Camera2dBundle::default(), TrainerCamera, config.layers()));
+}
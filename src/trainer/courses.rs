@@ -0,0 +1,300 @@
+//! Courses drawing the notes of a round, the built-in ones and those
+//! registered by other crates:
+//!
+//! ```no_run
+//! use rand::{Rng, RngCore};
+//! use staff_trainer::{register_course, CourseGenerator};
+//!
+//! /// Steps up and down from middle C, never leaving the treble staff.
+//! struct Stepwise;
+//!
+//! impl CourseGenerator for Stepwise {
+//!     fn name(&self) -> &'static str {
+//!         "Stepwise"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "steps on the treble staff"
+//!     }
+//!
+//!     fn next_pitch(&self, history: &[i32], rng: &mut dyn RngCore) -> i32 {
+//!         let last = history.last().copied().unwrap_or(0);
+//!         let step = if rng.gen() { 1 } else { -1 };
+//!         (last + step).clamp(0, 12)
+//!     }
+//! }
+//!
+//! register_course(Stepwise);
+//! ```
+//!
+//! Registered courses are on the course bar unless `TrainerPlugin::courses`
+//! lists others.
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex, RwLock,
+    },
+};
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::naming::pitch_class;
+
+/// The student's answer to a note.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Answer {
+    /// Pitch class answered, 0 for C
+    pub key: usize,
+    /// Where the note was written on the staff in the write modes
+    pub placed: Option<i32>,
+}
+
+impl Answer {
+    /// The pitch class of the note, and its staff position when written.
+    pub fn names(&self, pitch: i32) -> bool {
+        self.key == pitch_class(pitch) && self.placed.is_none_or(|placed| placed == pitch)
+    }
+}
+
+/// Draws the notes of a course. Pitches are staff positions, 0 for middle C
+/// and one per line or space, negative below.
+pub trait CourseGenerator: Send + Sync + 'static {
+    /// Name on the course bar, in links, saved results and homework files
+    fn name(&self) -> &'static str;
+
+    /// What the course drills, read out by screen readers
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// The next note of a round, after the notes of `history`. Rounds are
+    /// replayed from their seed, so all randomness comes from `rng`.
+    fn next_pitch(&self, history: &[i32], rng: &mut dyn RngCore) -> i32;
+
+    /// Whether `answer` is right for the note at `pitch`.
+    fn is_right(&self, pitch: i32, answer: &Answer) -> bool {
+        answer.names(pitch)
+    }
+}
+
+/// A course of the registry, saved by its name.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TrainCourse(&'static str);
+
+/// Notes drawn from a fixed set of staff positions.
+struct BuiltinCourse {
+    name: &'static str,
+    description: &'static str,
+    pitch: fn(&mut dyn RngCore) -> i32,
+}
+
+impl CourseGenerator for BuiltinCourse {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn next_pitch(&self, _history: &[i32], rng: &mut dyn RngCore) -> i32 {
+        (self.pitch)(rng)
+    }
+}
+
+const LINES: [i32; 7] = [0, 2, 4, 6, 8, 10, 12];
+const SPACES: [i32; 6] = [1, 3, 5, 7, 9, 11];
+
+impl TrainCourse {
+    pub const TREBLE_LINES: TrainCourse = TrainCourse("TrebleLines");
+    pub const TREBLE_SPACES: TrainCourse = TrainCourse("TrebleSpaces");
+    pub const TREBLE_ALL: TrainCourse = TrainCourse("TrebleAll");
+    pub const BASS_LINES: TrainCourse = TrainCourse("BassLines");
+    pub const BASS_SPACES: TrainCourse = TrainCourse("BassSpaces");
+    pub const BASS_ALL: TrainCourse = TrainCourse("BassAll");
+    pub const ALL: TrainCourse = TrainCourse("All");
+    pub const BUILTIN: [TrainCourse; 7] = [
+        Self::TREBLE_LINES,
+        Self::TREBLE_SPACES,
+        Self::TREBLE_ALL,
+        Self::BASS_LINES,
+        Self::BASS_SPACES,
+        Self::BASS_ALL,
+        Self::ALL,
+    ];
+
+    /// The registered course of that name.
+    pub fn named(name: &str) -> Option<TrainCourse> {
+        COURSES
+            .read()
+            .unwrap()
+            .iter()
+            .find(|generator| generator.name() == name)
+            .map(|generator| TrainCourse(generator.name()))
+    }
+
+    /// Every registered course, the built-in ones first.
+    pub fn all() -> Vec<TrainCourse> {
+        COURSES
+            .read()
+            .unwrap()
+            .iter()
+            .map(|generator| TrainCourse(generator.name()))
+            .collect()
+    }
+
+    /// The course's generator, the one of `ALL` for a course saved in a file
+    /// but not registered, like a course script still loading.
+    pub fn generator(&self) -> Arc<dyn CourseGenerator> {
+        let courses = COURSES.read().unwrap();
+        let find = |name: &str| {
+            courses
+                .iter()
+                .find(|generator| generator.name() == name)
+                .cloned()
+        };
+        find(self.0).unwrap_or_else(|| {
+            // Asked for every answer and every frame of the course bar
+            static WARNED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+            let mut warned = WARNED.lock().unwrap();
+            if !warned.contains(&self.0) {
+                println!(
+                    "Course {} isn't registered, drawing from {}",
+                    self.0,
+                    Self::ALL.0
+                );
+                warned.push(self.0);
+            }
+            find(Self::ALL.0).expect("built-in courses stay registered")
+        })
+    }
+
+    pub(super) fn name(self) -> &'static str {
+        self.0
+    }
+
+    /// The course of that name, registered or not yet. A name that isn't
+    /// registered is kept for the rest of the process, once however often
+    /// it is loaded or registered again.
+    pub(super) fn intern(name: &str) -> TrainCourse {
+        if let Some(course) = TrainCourse::named(name) {
+            return course;
+        }
+        static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+        let mut names = NAMES.lock().unwrap();
+        if let Some(interned) = names.get(name) {
+            return TrainCourse(interned);
+        }
+        let interned: &'static str = Box::leak(name.into());
+        names.insert(interned);
+        TrainCourse(interned)
+    }
+}
+
+impl AsRef<str> for TrainCourse {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+static COURSES: LazyLock<RwLock<Vec<Arc<dyn CourseGenerator>>>> = LazyLock::new(|| {
+    fn builtin(
+        name: &'static str,
+        description: &'static str,
+        pitch: fn(&mut dyn RngCore) -> i32,
+    ) -> Arc<dyn CourseGenerator> {
+        Arc::new(BuiltinCourse {
+            name,
+            description,
+            pitch,
+        })
+    }
+    RwLock::new(vec![
+        builtin(
+            TrainCourse::TREBLE_LINES.0,
+            "lines of the treble staff",
+            |rng| *LINES.choose(rng).unwrap(),
+        ),
+        builtin(
+            TrainCourse::TREBLE_SPACES.0,
+            "spaces of the treble staff",
+            |rng| *SPACES.choose(rng).unwrap(),
+        ),
+        builtin(TrainCourse::TREBLE_ALL.0, "the whole treble staff", |rng| {
+            rng.gen_range(0..13)
+        }),
+        builtin(
+            TrainCourse::BASS_LINES.0,
+            "lines of the bass staff",
+            |rng| -*LINES.choose(rng).unwrap(),
+        ),
+        builtin(
+            TrainCourse::BASS_SPACES.0,
+            "spaces of the bass staff",
+            |rng| -*SPACES.choose(rng).unwrap(),
+        ),
+        builtin(TrainCourse::BASS_ALL.0, "the whole bass staff", |rng| {
+            -rng.gen_range(0..13)
+        }),
+        builtin(TrainCourse::ALL.0, "both staves", |rng| {
+            -rng.gen_range(-12..13)
+        }),
+    ])
+});
+
+/// Bumped by every registration, for the course bar to catch up.
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// Adds a course for every app of the process, a course of the same name is
+/// replaced. Built-in courses stay, a course named like one isn't added.
+/// Courses registered after the app starts join the course bar.
+pub fn register_course(generator: impl CourseGenerator) -> TrainCourse {
+    let course = TrainCourse(generator.name());
+    if TrainCourse::BUILTIN.contains(&course) {
+        println!("Course {} is built in, not registering another", course.0);
+        return course;
+    }
+    let mut courses = COURSES.write().unwrap();
+    match courses.iter_mut().find(|g| g.name() == course.0) {
+        Some(registered) => *registered = Arc::new(generator),
+        None => courses.push(Arc::new(generator)),
+    }
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    course
+}
+
+/// Changes whenever a course is registered.
+pub(super) fn generation() -> usize {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+impl Serialize for TrainCourse {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0)
+    }
+}
+
+struct CourseVisitor;
+
+impl de::Visitor<'_> for CourseVisitor {
+    type Value = TrainCourse;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the name of a course")
+    }
+
+    // Unknown names load too, a course script may register them later
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<TrainCourse, E> {
+        Ok(TrainCourse::intern(name))
+    }
+}
+
+impl<'de> Deserialize<'de> for TrainCourse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(CourseVisitor)
+    }
+}
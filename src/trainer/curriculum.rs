@@ -0,0 +1,551 @@
+//! Ordered lessons introducing the grand staff a few notes at a time. Passing a
+//! round of a lesson fast and accurately enough unlocks the next one.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    accessibility::ACTIVATE_KEYS,
+    config::{TrainerAssets, TrainerConfig},
+    layout::ScaledUi,
+    profiles::Profile,
+    recording::InputSource,
+    settings::Settings,
+    versus::{spawn_button, Versus},
+    GameAction, GameButton, NoteAnswered, Screen, TrainSequence,
+};
+use crate::storage;
+
+pub(super) const PROGRESS_KEY: &str = "progress";
+
+pub(super) struct Lesson {
+    pub name: &'static str,
+    pub intro: &'static str,
+    pitches: &'static [i32],
+    /// Share of the notes answered right at the first attempt
+    pass_accuracy: f32,
+    /// Average time between two answers
+    pass_seconds_per_note: f32,
+}
+
+pub(super) const LESSONS: [Lesson; 9] = [
+    Lesson {
+        name: "Treble landmarks",
+        intro: "Middle C sits on its own ledger line below the treble staff. The \
+                treble clef curls around the G line, the second from the bottom. \
+                C5 fills the third space and G5 sits right above the staff.",
+        pitches: &[0, 4, 7, 11],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 4.,
+    },
+    Lesson {
+        name: "Treble lines",
+        intro: "The five lines of the treble staff are E G B D F from the bottom \
+                up. Find the G line first, then count on.",
+        pitches: &[2, 4, 6, 8, 10],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 3.,
+    },
+    Lesson {
+        name: "Treble spaces",
+        intro: "The four spaces of the treble staff spell F A C E from the bottom up.",
+        pitches: &[3, 5, 7, 9],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 3.,
+    },
+    Lesson {
+        name: "Treble ledger lines",
+        intro: "Ledger lines extend the staff, count on from the nearest landmark. \
+                Below the staff come B3 and A3 under middle C, above it G5, A5, \
+                B5 and C6.",
+        pitches: &[-2, -1, 0, 11, 12, 13, 14],
+        pass_accuracy: 0.85,
+        pass_seconds_per_note: 4.,
+    },
+    Lesson {
+        name: "Bass landmarks",
+        intro: "The dots of the bass clef surround the F line, the second from the \
+                top. C3 fills the second space, middle C sits on a ledger line \
+                above the staff and F2 right below it.",
+        pitches: &[0, -4, -7, -11],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 4.,
+    },
+    Lesson {
+        name: "Bass lines",
+        intro: "The five lines of the bass staff are G B D F A from the bottom up.",
+        pitches: &[-10, -8, -6, -4, -2],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 3.,
+    },
+    Lesson {
+        name: "Bass spaces",
+        intro: "The four spaces of the bass staff are A C E G from the bottom up.",
+        pitches: &[-9, -7, -5, -3],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 3.,
+    },
+    Lesson {
+        name: "Bass ledger lines",
+        intro: "Below the bass staff come F2, E2, D2 and C2, above it B3, middle C \
+                and D4.",
+        pitches: &[-14, -13, -12, -11, -1, 0, 1],
+        pass_accuracy: 0.85,
+        pass_seconds_per_note: 4.,
+    },
+    Lesson {
+        name: "Grand staff",
+        intro: "Every note of both staves, from E2 to A5. Read them as fast as you \
+                can.",
+        pitches: &[
+            -12, -11, -10, -9, -8, -7, -6, -5, -4, -3, -2, -1, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10,
+            11, 12,
+        ],
+        pass_accuracy: 0.9,
+        pass_seconds_per_note: 2.5,
+    },
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct LessonScore {
+    pub accuracy: f32,
+    pub seconds_per_note: f32,
+}
+
+impl Lesson {
+    pub fn random_pitch(&self, rng: &mut ChaCha8Rng) -> i32 {
+        *self.pitches.choose(rng).unwrap()
+    }
+
+    pub fn criteria(&self) -> String {
+        format!(
+            "Pass with {:.0}% correct, under {:.1} s per note",
+            self.pass_accuracy * 100.,
+            self.pass_seconds_per_note
+        )
+    }
+
+    fn passes(&self, score: LessonScore) -> bool {
+        score.accuracy >= self.pass_accuracy && score.seconds_per_note <= self.pass_seconds_per_note
+    }
+}
+
+/// Passed lessons and best scores, kept across sessions in the student's
+/// profile.
+#[derive(Resource, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Progress {
+    /// Names of the passed lessons, so reordering the lessons keeps them
+    passed: BTreeSet<String>,
+    best: BTreeMap<String, LessonScore>,
+}
+
+impl Progress {
+    pub fn load(profile: &Profile) -> Self {
+        let Some(contents) = storage::read(&profile.key(PROGRESS_KEY)) else {
+            return default();
+        };
+        match ron::from_str(&contents) {
+            Ok(progress) => progress,
+            Err(err) => {
+                println!("Invalid lesson progress, starting over: {}", err);
+                default()
+            }
+        }
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&profile.key(PROGRESS_KEY), &contents));
+        if let Err(err) = result {
+            println!("Failed to save lesson progress: {}", err);
+        }
+    }
+
+    pub fn is_passed(&self, lesson: usize) -> bool {
+        self.passed.contains(LESSONS[lesson].name)
+    }
+
+    /// The first lesson is always open, the others once the one before is
+    /// passed.
+    pub fn is_unlocked(&self, lesson: usize) -> bool {
+        lesson == 0 || self.is_passed(lesson - 1)
+    }
+
+    pub fn best(&self, lesson: usize) -> Option<LessonScore> {
+        self.best.get(LESSONS[lesson].name).copied()
+    }
+
+    /// Keeps the score of a finished round, returns whether it passed.
+    pub fn record(&mut self, lesson: usize, score: LessonScore) -> bool {
+        let name = LESSONS[lesson].name;
+        let better = self.best(lesson).is_none_or(|best| {
+            (score.accuracy, -score.seconds_per_note) > (best.accuracy, -best.seconds_per_note)
+        });
+        if better {
+            self.best.insert(name.to_string(), score);
+        }
+        let passed = LESSONS[lesson].passes(score);
+        if passed {
+            self.passed.insert(name.to_string());
+        }
+        passed
+    }
+}
+
+/// Lesson picked on the lesson list, and the clock of the running round.
+#[derive(Resource, Default)]
+pub(super) struct Curriculum {
+    /// Lesson shown on the intro screen
+    pub selected: usize,
+    /// Seed of the timed round and the time it started
+    started: Option<(u64, Duration)>,
+    /// Lesson, score and outcome of the last finished round
+    pub result: Option<(usize, LessonScore, bool)>,
+}
+
+/// Times the lesson rounds from their start, reading the first note counts
+/// too, and records them once finished. Retry rounds don't count, they only
+/// drill mistakes.
+pub(super) fn lesson_progress_system(
+    mut answers: EventReader<NoteAnswered>,
+    mut curriculum: ResMut<Curriculum>,
+    mut progress: ResMut<Progress>,
+    train: Res<TrainSequence>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    let timed = train.lesson.is_some() && !train.retry;
+    let timing = curriculum.started.map(|(seed, _)| seed) == Some(train.seed);
+    if timed && !timing && train.next_key == 0 {
+        curriculum.started = Some((train.seed, now));
+    }
+    for _ in answers.read() {
+        let Some(lesson) = train.lesson.filter(|_| !train.retry) else {
+            continue;
+        };
+        let Some((seed, started)) = curriculum.started else {
+            continue;
+        };
+        if seed != train.seed || train.next_key < train.notes.len() {
+            continue;
+        }
+
+        let num_notes = train.notes.len();
+        let correct = train.notes.iter().filter(|n| n.is_correct()).count();
+        let score = LessonScore {
+            accuracy: correct as f32 / num_notes as f32,
+            seconds_per_note: (now - started).as_secs_f32() / num_notes as f32,
+        };
+        let passed = progress.record(lesson, score);
+        curriculum.started = None;
+        curriculum.result = Some((lesson, score, passed));
+    }
+}
+
+/// Names the running lesson and its criteria, then the outcome of the round.
+#[derive(Component)]
+pub(super) struct LessonBanner;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn lesson_banner_system(
+    mut commands: Commands,
+    mut banners: Query<(Entity, &mut Text), With<LessonBanner>>,
+    curriculum: Res<Curriculum>,
+    train: Res<TrainSequence>,
+    versus: Option<Res<Versus>>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let lesson = train.lesson.filter(|_| versus.is_none());
+    let Some(index) = lesson else {
+        for (entity, _) in &banners {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if !train.is_changed() && !curriculum.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let lesson = &LESSONS[index];
+    let finished = train.next_key == train.notes.len();
+    let text = match curriculum.result {
+        Some((result, score, passed)) if result == index && finished => {
+            let outcome = match (passed, index + 1 < LESSONS.len()) {
+                (true, true) => "Passed, the next lesson is unlocked",
+                (true, false) => "Passed, every lesson is done",
+                (false, _) => "Not passed yet",
+            };
+            format!(
+                "{}: {:.0}% correct, {:.1} s per note. {}",
+                lesson.name,
+                score.accuracy * 100.,
+                score.seconds_per_note,
+                outcome
+            )
+        }
+        _ => format!("{}. {}", lesson.name, lesson.criteria()),
+    };
+    let style = TextStyle {
+        font: assets.font(),
+        font_size: 24.0,
+        color: settings.theme.palette().ink,
+    };
+    if banners.is_empty() {
+        commands.spawn((
+            TextBundle::from_section(text, style).with_style(config.corner_style()),
+            LessonBanner,
+            ScaledUi,
+        ));
+    } else {
+        for (_, mut banner) in &mut banners {
+            banner.sections[0] = TextSection::new(text.clone(), style.clone());
+        }
+    }
+}
+
+/// Root node of the lesson list and of the lesson intro.
+#[derive(Component)]
+pub(super) struct LessonScreen;
+
+#[derive(Component, Clone, Copy)]
+pub(super) enum LessonButton {
+    /// Shows the intro of an unlocked lesson
+    Open(usize),
+    Start,
+    Back,
+}
+
+fn spawn_screen<'a>(
+    commands: &'a mut Commands,
+    config: &TrainerConfig,
+    settings: &Settings,
+) -> EntityCommands<'a> {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.),
+                ..config.region_style()
+            },
+            background_color: settings.theme.palette().background.into(),
+            focus_policy: bevy::ui::FocusPolicy::Block,
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        LessonScreen,
+        ScaledUi,
+    ))
+}
+
+pub(super) fn lessons_screen_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    progress: Res<Progress>,
+) {
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    spawn_screen(&mut commands, &config, &settings).with_children(|parent| {
+        for (index, lesson) in LESSONS.iter().enumerate() {
+            let unlocked = progress.is_unlocked(index);
+            let state = if progress.is_passed(index) {
+                "passed"
+            } else if unlocked {
+                "open"
+            } else {
+                "locked"
+            };
+            let best = progress
+                .best(index)
+                .map(|best| format!(", best {:.0}%", best.accuracy * 100.))
+                .unwrap_or_default();
+            let label = format!("{}. {} ({}{})", index + 1, lesson.name, state, best);
+            let color = if unlocked {
+                palette.button_text
+            } else {
+                palette.button_text.with_alpha(0.4)
+            };
+            spawn_button(
+                parent,
+                LessonButton::Open(index),
+                Style {
+                    width: Val::Px(420.0),
+                    height: Val::Px(44.0),
+                    ..default()
+                },
+                TextBundle::from_section(
+                    label.clone(),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 20.0,
+                        color,
+                    },
+                ),
+                label,
+                palette,
+            );
+        }
+        spawn_button(
+            parent,
+            LessonButton::Back,
+            Style {
+                width: Val::Px(420.0),
+                height: Val::Px(44.0),
+                ..default()
+            },
+            TextBundle::from_section(
+                "Back",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    color: palette.button_text,
+                },
+            ),
+            "Back to the trainer".to_string(),
+            palette,
+        );
+    });
+}
+
+pub(super) fn lesson_intro_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    curriculum: Res<Curriculum>,
+) {
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    let lesson = &LESSONS[curriculum.selected];
+    let text = |value: &str, font_size: f32| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: palette.ink,
+            },
+        )
+        .with_style(Style {
+            max_width: Val::Px(600.),
+            ..default()
+        })
+    };
+    spawn_screen(&mut commands, &config, &settings).with_children(|parent| {
+        parent.spawn(text(lesson.name, 40.));
+        parent.spawn(text(lesson.intro, 22.));
+        parent.spawn(text(&lesson.criteria(), 22.));
+        for (button, label, name) in [
+            (
+                LessonButton::Start,
+                "Start",
+                format!("Start lesson {}", lesson.name),
+            ),
+            (
+                LessonButton::Back,
+                "Back",
+                "Back to the lessons".to_string(),
+            ),
+        ] {
+            spawn_button(
+                parent,
+                button,
+                Style {
+                    width: Val::Px(320.0),
+                    height: Val::Px(50.0),
+                    ..default()
+                },
+                TextBundle::from_section(
+                    label,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 22.0,
+                        color: palette.button_text,
+                    },
+                ),
+                name,
+                palette,
+            );
+        }
+    });
+}
+
+pub(super) fn lesson_screen_cleanup(
+    mut commands: Commands,
+    screens: Query<Entity, With<LessonScreen>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type LessonButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d LessonButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn lesson_button_system(
+    mut interaction_query: LessonButtonQuery,
+    mut curriculum: ResMut<Curriculum>,
+    progress: Res<Progress>,
+    settings: Res<Settings>,
+    screen: Res<State<Screen>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut actions: EventWriter<GameAction>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            LessonButton::Open(index) if progress.is_unlocked(index) => {
+                curriculum.selected = index;
+                next_screen.set(Screen::LessonIntro);
+            }
+            LessonButton::Open(_) => {}
+            LessonButton::Start => {
+                // Played on the trainer screen, where it is recorded
+                let source = if keys.any_just_pressed(ACTIVATE_KEYS) {
+                    InputSource::Keyboard
+                } else {
+                    InputSource::Pointer
+                };
+                actions.send(GameAction {
+                    button: GameButton::Lesson(curriculum.selected),
+                    source,
+                });
+                next_screen.set(Screen::Trainer);
+            }
+            LessonButton::Back => next_screen.set(match screen.get() {
+                Screen::LessonIntro => Screen::Lessons,
+                _ => Screen::Trainer,
+            }),
+        }
+    }
+}
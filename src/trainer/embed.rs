@@ -0,0 +1,188 @@
+//! Commands of the page or app hosting the trainer, see `web.rs` for the
+//! JavaScript API sending them, and the drill it starts with.
+
+use bevy::prelude::*;
+use rand::Rng;
+
+use super::{
+    recording::InputSource, settings::Settings, GameAction, GameButton, TrainCourse, TrainSequence,
+    STAFF_MAX_NOTES,
+};
+
+#[derive(Event, Clone, Debug)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub(crate) enum HostCommand {
+    /// Starts a round of the course named like its button, from the seed when
+    /// given
+    Start {
+        course: String,
+        seed: Option<String>,
+    },
+    /// Changes the settings named in a JSON object, like `{"sound": false}`
+    Settings(String),
+    Pause,
+    Resume,
+    /// Imports the RON text of an assignment file into the homework
+    ImportHomework(String),
+}
+
+/// Buttons and answers are ignored and the clock is stopped.
+#[derive(Resource)]
+pub(super) struct Paused;
+
+/// Seeds are numbers, or any text hashed into one so links can carry words.
+pub(crate) fn parse_seed(seed: &str) -> u64 {
+    seed.parse().unwrap_or_else(|_| {
+        // FNV-1a, stable across Rust versions unlike the std hashers
+        seed.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        })
+    })
+}
+
+pub(super) fn parse_course(course: &str) -> anyhow::Result<TrainCourse> {
+    TrainCourse::all()
+        .into_iter()
+        .find(|c| c.as_ref().eq_ignore_ascii_case(course))
+        .ok_or_else(|| anyhow::anyhow!("unknown course {}", course))
+}
+
+/// Drill to start with, from the `course`, `notes` and `seed` parameters of the
+/// page URL or the command line flags of the same names.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub(crate) struct LaunchOptions {
+    course: Option<TrainCourse>,
+    notes: Option<usize>,
+    seed: Option<u64>,
+    /// Practise as this profile instead of picking one, on the command line
+    pub profile: Option<String>,
+}
+
+#[cfg_attr(target_os = "android", allow(dead_code))]
+impl LaunchOptions {
+    pub const NAMES: [&'static str; 3] = ["course", "notes", "seed"];
+
+    /// Reads the parameters of `NAMES`, the others are left to the caller.
+    pub fn parse<'a>(
+        parameters: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> anyhow::Result<Self> {
+        let mut options = LaunchOptions::default();
+        for (name, value) in parameters {
+            match name {
+                "course" => options.course = Some(parse_course(value)?),
+                "notes" => {
+                    let notes = value
+                        .parse()
+                        .ok()
+                        .filter(|notes| (1..=STAFF_MAX_NOTES).contains(notes))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("notes are 1 to {}, not {}", STAFF_MAX_NOTES, value)
+                        })?;
+                    options.notes = Some(notes);
+                }
+                "seed" => options.seed = Some(parse_seed(value)),
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Starts the drill of the launch options, once the student's profile is
+/// picked.
+pub(super) fn launch_system(
+    mut commands: Commands,
+    launch: Res<LaunchOptions>,
+    mut train: ResMut<TrainSequence>,
+    settings: Res<Settings>,
+    mut actions: EventWriter<GameAction>,
+) {
+    commands.remove_resource::<LaunchOptions>();
+    let course = launch.course.unwrap_or(train.course);
+    let button = match *launch {
+        LaunchOptions {
+            course: None,
+            notes: None,
+            seed: None,
+            ..
+        } => return,
+        LaunchOptions {
+            notes: None,
+            seed: None,
+            ..
+        } => GameButton::Course(course),
+        LaunchOptions { notes, seed, .. } => GameButton::Seeded {
+            course,
+            notes: notes.unwrap_or(settings.notes_per_round),
+            seed: seed.unwrap_or_else(|| train.rng.gen()),
+        },
+    };
+    actions.send(GameAction {
+        button,
+        source: InputSource::Host,
+    });
+}
+
+/// The settings with the fields of the JSON object changed.
+fn merge_settings(settings: &Settings, changes: &str) -> anyhow::Result<Settings> {
+    let mut merged = serde_json::to_value(settings)?;
+    let serde_json::Value::Object(changes) = serde_json::from_str(changes)? else {
+        anyhow::bail!("settings are changed with a JSON object");
+    };
+    let fields = merged.as_object_mut().expect("settings are a struct");
+    for (name, value) in changes {
+        if !fields.contains_key(&name) {
+            anyhow::bail!("unknown setting {}", name);
+        }
+        fields.insert(name, value);
+    }
+    Ok(serde_json::from_value(merged)?)
+}
+
+pub(super) fn host_command_system(
+    mut commands: Commands,
+    mut host: EventReader<HostCommand>,
+    mut actions: EventWriter<GameAction>,
+    mut settings: ResMut<Settings>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    for command in host.read() {
+        match command {
+            HostCommand::Start { course, seed } => {
+                let course = match parse_course(course) {
+                    Ok(course) => course,
+                    Err(err) => {
+                        println!("Can't start: {}", err);
+                        continue;
+                    }
+                };
+                let button = match seed {
+                    Some(seed) => GameButton::Seeded {
+                        course,
+                        notes: settings.notes_per_round,
+                        seed: parse_seed(seed),
+                    },
+                    None => GameButton::Course(course),
+                };
+                actions.send(GameAction {
+                    button,
+                    source: InputSource::Host,
+                });
+            }
+            HostCommand::Settings(changes) => match merge_settings(&settings, changes) {
+                Ok(merged) => *settings = merged,
+                Err(err) => println!("Settings unchanged: {}", err),
+            },
+            HostCommand::Pause => {
+                time.pause();
+                commands.insert_resource(Paused);
+            }
+            HostCommand::Resume => {
+                time.unpause();
+                commands.remove_resource::<Paused>();
+            }
+            // The homework list imports it once a profile is picked
+            HostCommand::ImportHomework(_) => {}
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
+
+use super::{config::TrainerConfig, layout::StaffLayout, TrainSequence};
+
+// Engraving sizes in design units
+const LINE_THICKNESS: f32 = 1.5;
+const FINAL_BAR_THICKNESS: f32 = 5.;
+const FINAL_BAR_GAP: f32 = 4.;
+const NOTE_HEAD_HALF_WIDTH: f32 = 16.;
+pub(super) const LEDGER_HALF_WIDTH: f32 = 26.;
+const STEM_LENGTH: i32 = 7;
+const BRACE_GAP: f32 = 6.;
+const BRACE_WIDTH: f32 = 18.;
+const BRACE_SEGMENTS: usize = 48;
+
+/// Top and bottom line of each staff, middle C is pitch 0.
+const TREBLE_LINES: (i32, i32) = (2, 10);
+const BASS_LINES: (i32, i32) = (-10, -2);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub(super) enum EngravingMesh {
+    /// Staff lines, bar lines and the grand staff brace
+    System,
+    /// Stems and ledger lines of the current round
+    Notes,
+}
+
+/// Collects thick line segments and polygons into one triangle mesh.
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    fn line(&mut self, from: Vec2, to: Vec2, thickness: f32) {
+        let dir = (to - from).normalize_or_zero();
+        let side = dir.perp() * thickness * 0.5;
+        self.quad([from - side, from + side, to + side, to - side]);
+    }
+
+    fn quad(&mut self, corners: [Vec2; 4]) {
+        let base = self.positions.len() as u32;
+        self.positions
+            .extend(corners.iter().map(|c| [c.x, c.y, 0.]));
+        self.indices
+            .extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    fn build(self) -> Mesh {
+        let count = self.positions.len();
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; count])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; count])
+        .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+/// Middle line of the staff a note is written on, stems point away from it.
+fn middle_line(pitch: i32) -> i32 {
+    if pitch >= 0 {
+        (TREBLE_LINES.0 + TREBLE_LINES.1) / 2
+    } else {
+        (BASS_LINES.0 + BASS_LINES.1) / 2
+    }
+}
+
+/// Pitches of the ledger lines needed to write `pitch` on the grand staff.
+pub(super) fn ledger_lines(pitch: i32) -> Vec<i32> {
+    if pitch > TREBLE_LINES.1 {
+        (TREBLE_LINES.1 + 2..=pitch).step_by(2).collect()
+    } else if pitch < BASS_LINES.0 {
+        (pitch..=BASS_LINES.0 - 2)
+            .rev()
+            .filter(|p| p % 2 == 0)
+            .collect()
+    } else if pitch == 0 {
+        // Middle C sits on its own line between the two staves
+        vec![0]
+    } else {
+        vec![]
+    }
+}
+
+/// Returns whether the stem goes up, and the pitch the stem ends at. Stems of
+/// notes far outside the staff are lengthened to reach the middle line.
+pub(super) fn stem(pitch: i32) -> (bool, i32) {
+    let middle = middle_line(pitch);
+    if pitch < middle {
+        (true, (pitch + STEM_LENGTH).max(middle))
+    } else {
+        (false, (pitch - STEM_LENGTH).min(middle))
+    }
+}
+
+fn build_system_mesh(layout: &StaffLayout) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    let thickness = (LINE_THICKNESS * layout.scale).max(1.);
+    let left = layout.staff_x;
+    let right = layout.staff_x + layout.staff_width;
+
+    for (bottom, top) in [TREBLE_LINES, BASS_LINES] {
+        for pitch in (bottom..=top).step_by(2) {
+            let y = layout.pitch_to_y(pitch);
+            builder.line(Vec2::new(left, y), Vec2::new(right, y), thickness);
+        }
+    }
+
+    // System bar line on the left, final bar line on the right
+    let top = layout.pitch_to_y(TREBLE_LINES.1) + thickness * 0.5;
+    let bottom = layout.pitch_to_y(BASS_LINES.0) - thickness * 0.5;
+    builder.line(Vec2::new(left, top), Vec2::new(left, bottom), thickness);
+
+    let thick = FINAL_BAR_THICKNESS * layout.scale;
+    let x = right - thick * 0.5;
+    builder.line(Vec2::new(x, top), Vec2::new(x, bottom), thick);
+    let x = right - thick - FINAL_BAR_GAP * layout.scale;
+    builder.line(Vec2::new(x, top), Vec2::new(x, bottom), thickness);
+
+    // Brace, a centerline bent into a '{' with tapered ends
+    let brace_right = left - BRACE_GAP * layout.scale;
+    let width = BRACE_WIDTH * layout.scale;
+    let max_thickness = width * 0.3;
+    let point = |t: f32| {
+        // 0 in the middle of the brace, 1 at either tip
+        let s = (2. * t - 1.).abs();
+        let x =
+            brace_right - width * (1. - s).powi(3) * 0.7 - width * 0.3 + width * 0.3 * s.powi(6);
+        let y = top + (bottom - top) * t;
+        let half = (max_thickness * (PI * s).sin().max(0.).powf(0.8) + thickness) * 0.5;
+        (Vec2::new(x - half, y), Vec2::new(x + half, y))
+    };
+    for seg in 0..BRACE_SEGMENTS {
+        let (a0, a1) = point(seg as f32 / BRACE_SEGMENTS as f32);
+        let (b0, b1) = point((seg + 1) as f32 / BRACE_SEGMENTS as f32);
+        builder.quad([a0, a1, b1, b0]);
+    }
+
+    builder.build()
+}
+
+fn build_notes_mesh(layout: &StaffLayout, train: &TrainSequence) -> Mesh {
+    let mut builder = MeshBuilder::default();
+    let thickness = (LINE_THICKNESS * layout.scale).max(1.);
+    let note_space = layout.note_space(train.notes.len());
+    let ledger_half = (LEDGER_HALF_WIDTH * layout.scale).min(note_space * 0.45);
+    let head_half = NOTE_HEAD_HALF_WIDTH * layout.scale;
+
+    for note in train.notes.iter() {
+        let Some(pitch) = train.shown_pitch(note) else {
+            continue;
+        };
+        let x = layout.index_to_x(note.index, note_space);
+
+        for pitch in ledger_lines(pitch) {
+            let y = layout.pitch_to_y(pitch);
+            builder.line(
+                Vec2::new(x - ledger_half, y),
+                Vec2::new(x + ledger_half, y),
+                thickness,
+            );
+        }
+
+        let (up, end) = stem(pitch);
+        let stem_x = if up {
+            x + head_half - thickness * 0.5
+        } else {
+            x - head_half + thickness * 0.5
+        };
+        builder.line(
+            Vec2::new(stem_x, layout.pitch_to_y(pitch)),
+            Vec2::new(stem_x, layout.pitch_to_y(end)),
+            thickness,
+        );
+    }
+
+    builder.build()
+}
+
+pub(super) fn engraving_setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<TrainerConfig>,
+) {
+    let material = materials.add(ColorMaterial::from(Color::BLACK));
+    for kind in [EngravingMesh::System, EngravingMesh::Notes] {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(MeshBuilder::default().build())),
+                material: material.clone(),
+                ..default()
+            },
+            kind,
+            config.layers(),
+        ));
+    }
+}
+
+/// Rebuilds the engraving meshes, only when the layout or the round changed.
+pub(super) fn engraving_update(
+    engravings: Query<(&EngravingMesh, &Mesh2dHandle)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+) {
+    let layout_changed = layout.is_changed();
+    let train_changed = train.is_changed();
+    if !layout_changed && !train_changed {
+        return;
+    }
+    for (kind, handle) in &engravings {
+        let mesh = match kind {
+            EngravingMesh::System if layout_changed => build_system_mesh(&layout),
+            EngravingMesh::Notes => build_notes_mesh(&layout, &train),
+            _ => continue,
+        };
+        meshes.insert(&handle.0, mesh);
+    }
+}
@@ -0,0 +1,94 @@
+//! Note heads flashing in the color of each answer as it is given.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{
+    config::{TrainerAssets, TrainerConfig},
+    layout::StaffLayout,
+    settings::{FeedbackStyle, Settings},
+    NoteAnswered, TrainSequence, STAFF_ELEMENTS, STAFF_ELEM_IDX_NOTE_4,
+};
+
+const FLASH_DURATION: Duration = Duration::from_millis(400);
+/// Size of the flash when it fades out, relative to the note head
+const FLASH_GROWTH: f32 = 0.8;
+
+#[derive(Component)]
+pub(super) struct Flash {
+    index: usize,
+    timer: Timer,
+}
+
+pub(super) fn flash_spawn_system(
+    mut commands: Commands,
+    mut answers: EventReader<NoteAnswered>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    // Would give away the answers before the round is over
+    if !settings.flash || settings.feedback == FeedbackStyle::EndOfRound {
+        answers.clear();
+        return;
+    }
+    let palette = settings.theme.palette();
+    let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_NOTE_4];
+    for answer in answers.read() {
+        let color = if answer.correct {
+            palette.correct
+        } else {
+            palette.wrong
+        };
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color, ..default() },
+                texture: assets.load(asset_path),
+                // Placed by flash_update_system before it is drawn
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            Flash {
+                index: answer.index,
+                timer: Timer::new(FLASH_DURATION, TimerMode::Once),
+            },
+            config.layers(),
+        ));
+    }
+}
+
+/// Grows and fades the flashes over the notes they belong to.
+pub(super) fn flash_update_system(
+    mut commands: Commands,
+    mut flashes: Query<(
+        Entity,
+        &mut Flash,
+        &mut Sprite,
+        &mut Transform,
+        &mut Visibility,
+    )>,
+    time: Res<Time>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+) {
+    let note_space = layout.note_space(train.notes.len());
+    for (entity, mut flash, mut sprite, mut transform, mut visibility) in &mut flashes {
+        flash.timer.tick(time.delta());
+        let Some(note) = train.notes.get(flash.index) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        if flash.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let fraction = flash.timer.fraction();
+        let pitch = train.shown_pitch(note).unwrap_or(note.pitch);
+        let x = layout.index_to_x(flash.index, note_space);
+        *visibility = Visibility::Visible;
+        sprite.color.set_alpha(1. - fraction);
+        *transform = Transform::from_xyz(x, layout.pitch_to_y(pitch), 0.7)
+            .with_scale(Vec3::splat(layout.scale * (1. + FLASH_GROWTH * fraction)));
+    }
+}
@@ -0,0 +1,210 @@
+use bevy::{
+    input::gamepad::{GamepadConnection, GamepadConnectionEvent},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::{TrainerAssets, TrainerConfig},
+    layout::ScaledUi,
+    naming::{NameFont, NameLabel, PITCH_CLASSES},
+    recording::InputSource,
+    settings::Settings,
+    GameAction, GameButton, TrainCourse, TrainSequence,
+};
+
+/// Gamepad buttons of the answers and the course navigation, stored with the
+/// settings so it can be changed in `settings.ron`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct GamepadMapping {
+    /// Answer key of each pitch class, from C to B
+    pub answers: [GamepadButtonType; PITCH_CLASSES],
+    pub previous_course: GamepadButtonType,
+    pub next_course: GamepadButtonType,
+    pub retry: GamepadButtonType,
+    /// Shows or hides the mapping overlay
+    pub overlay: GamepadButtonType,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+        GamepadMapping {
+            // C D E on the d-pad, F G A B on the face buttons, both going
+            // around clockwise from the left
+            answers: [DPadLeft, DPadUp, DPadRight, West, North, East, South],
+            previous_course: LeftTrigger,
+            next_course: RightTrigger,
+            retry: Select,
+            overlay: Start,
+        }
+    }
+}
+
+impl GamepadMapping {
+    /// The triggers cycle through the course bar's `courses`.
+    fn action(
+        &self,
+        button: GamepadButtonType,
+        course: TrainCourse,
+        courses: &[TrainCourse],
+    ) -> Option<GameButton> {
+        if let Some(key) = self.answers.iter().position(|&b| b == button) {
+            return Some(GameButton::Answer(key));
+        }
+        if button == self.retry {
+            return Some(GameButton::Retry);
+        }
+        let index = courses.iter().position(|&c| c == course).unwrap_or(0);
+        let step = if button == self.next_course {
+            1
+        } else if button == self.previous_course {
+            courses.len().checked_sub(1)?
+        } else {
+            return None;
+        };
+        let next = *courses.get((index + step) % courses.len().max(1))?;
+        Some(GameButton::Course(next))
+    }
+}
+
+fn button_label(button: GamepadButtonType) -> String {
+    use GamepadButtonType::*;
+    match button {
+        South => "A / Cross".to_string(),
+        East => "B / Circle".to_string(),
+        West => "X / Square".to_string(),
+        North => "Y / Triangle".to_string(),
+        LeftTrigger => "LB / L1".to_string(),
+        RightTrigger => "RB / R1".to_string(),
+        LeftTrigger2 => "LT / L2".to_string(),
+        RightTrigger2 => "RT / R2".to_string(),
+        DPadUp => "D-pad up".to_string(),
+        DPadDown => "D-pad down".to_string(),
+        DPadLeft => "D-pad left".to_string(),
+        DPadRight => "D-pad right".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Panel listing the gamepad mapping, shown when a gamepad connects.
+#[derive(Component)]
+pub(super) struct GamepadOverlay;
+
+/// Turns gamepad presses into `GameAction`s, next to the on-screen buttons.
+pub(super) fn gamepad_system(
+    buttons: Res<ButtonInput<GamepadButton>>,
+    settings: Res<Settings>,
+    train: Res<TrainSequence>,
+    mut actions: EventWriter<GameAction>,
+    config: Res<TrainerConfig>,
+) {
+    for pressed in buttons.get_just_pressed() {
+        if let Some(button) =
+            settings
+                .gamepad
+                .action(pressed.button_type, train.course, &config.courses())
+        {
+            actions.send(GameAction {
+                button,
+                source: InputSource::Gamepad,
+            });
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn gamepad_overlay_system(
+    mut commands: Commands,
+    mut connections: EventReader<GamepadConnectionEvent>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut overlays: Query<(Entity, &mut Visibility), With<GamepadOverlay>>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    let connected = connections
+        .read()
+        .filter(|e| matches!(e.connection, GamepadConnection::Connected(_)))
+        .count()
+        > 0;
+    let toggled = buttons
+        .get_just_pressed()
+        .any(|b| b.button_type == settings.gamepad.overlay);
+
+    if !settings.is_changed() {
+        for (_, mut visibility) in &mut overlays {
+            if connected || (toggled && *visibility == Visibility::Hidden) {
+                *visibility = Visibility::Inherited;
+            } else if toggled {
+                *visibility = Visibility::Hidden;
+            }
+        }
+        return;
+    }
+
+    // Rebuilt with the settings, the mapping and the note names may change
+    let mut visible = if connected {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for (entity, visibility) in &overlays {
+        visible = *visibility;
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette = settings.theme.palette();
+    let style = TextStyle {
+        font: assets.font(),
+        font_size: 16.0,
+        color: palette.button_text,
+    };
+    let mapping = &settings.gamepad;
+    let lines = settings
+        .naming
+        .names(name_font.loaded)
+        .into_iter()
+        .zip(mapping.answers)
+        .map(|(name, button)| (name.to_string(), button))
+        .chain([
+            ("Previous course".to_string(), mapping.previous_course),
+            ("Next course".to_string(), mapping.next_course),
+            ("Retry".to_string(), mapping.retry),
+            ("Show or hide this".to_string(), mapping.overlay),
+        ]);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Percent((1. - config.region.max.x) * 100.),
+                    top: Val::Percent((config.region.min.y + config.region.height() * 0.3) * 100.),
+                    margin: UiRect::right(Val::Px(8.)),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.)),
+                    ..default()
+                },
+                background_color: palette.button.with_alpha(0.85).into(),
+                border_radius: BorderRadius::all(Val::Px(4.)),
+                visibility: visible,
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            GamepadOverlay,
+            ScaledUi,
+        ))
+        .with_children(|parent| {
+            for (action, button) in lines {
+                parent.spawn((
+                    TextBundle::from_section(
+                        format!("{}: {}", button_label(button), action),
+                        style.clone(),
+                    ),
+                    NameLabel,
+                ));
+            }
+        });
+}
@@ -0,0 +1,604 @@
+//! Assignments handed out by a teacher as files, shown as a homework list, and
+//! the reports students sign off and send back.
+//!
+//! An assignment is a RON file like:
+//!
+//! ```ron
+//! Assignment(
+//!     title: "Week 3",
+//!     teacher: "Ms. Lee",
+//!     due: Some("2026-11-01"),
+//!     drills: [
+//!         (course: "TrebleLines", seed: 42, notes: 16, required_accuracy: 0.9),
+//!         (course: "BassAll", seed: 7, notes: 24, required_accuracy: 0.8),
+//!     ],
+//! )
+//! ```
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    accessibility::ACTIVATE_KEYS,
+    calendar::today,
+    config::{TrainerAssets, TrainerConfig},
+    layout::ScaledUi,
+    profiles::Profile,
+    recording::InputSource,
+    settings::Settings,
+    versus::spawn_button,
+    GameAction, GameButton, HostCommand, NoteAnswered, Screen, TrainCourse, TrainSequence,
+    STAFF_MAX_NOTES,
+};
+use crate::{share, storage};
+
+pub(super) const HOMEWORK_KEY: &str = "homework";
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+const IMPORT_HINT: &str = "Drop an assignment file on the window to import it";
+#[cfg(target_os = "android")]
+const IMPORT_HINT: &str = "Open an assignment file with Staff Trainer to import it";
+#[cfg(target_arch = "wasm32")]
+const IMPORT_HINT: &str = "";
+
+/// One round the teacher asks for, the seed makes it the same for the whole
+/// class.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct Drill {
+    pub course: TrainCourse,
+    pub seed: u64,
+    pub notes: usize,
+    /// Share of the notes to answer right at the first attempt
+    pub required_accuracy: f32,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct Assignment {
+    pub title: String,
+    #[serde(default)]
+    pub teacher: String,
+    /// Date as `YYYY-MM-DD`
+    #[serde(default)]
+    pub due: Option<String>,
+    pub drills: Vec<Drill>,
+}
+
+impl Assignment {
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let assignment: Assignment = ron::from_str(contents)?;
+        if assignment.drills.is_empty() {
+            anyhow::bail!("the assignment has no drills");
+        }
+        if let Some(drill) = assignment
+            .drills
+            .iter()
+            .find(|d| !(1..=STAFF_MAX_NOTES).contains(&d.notes))
+        {
+            anyhow::bail!(
+                "drills have 1 to {} notes, not {}",
+                STAFF_MAX_NOTES,
+                drill.notes
+            );
+        }
+        if let Some(drill) = assignment
+            .drills
+            .iter()
+            .find(|d| TrainCourse::named(d.course.as_ref()).is_none())
+        {
+            anyhow::bail!("unknown course {}", drill.course.as_ref());
+        }
+        Ok(assignment)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct NoteReport {
+    /// Note shown, with its octave
+    pub note: String,
+    /// Name of the last key pressed
+    pub answer: String,
+    pub correct: bool,
+    pub attempts: u32,
+    /// Time taken to answer, from the previous answer or the start of the round
+    pub seconds: f32,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct DrillReport {
+    pub accuracy: f32,
+    pub passed: bool,
+    pub seconds: f32,
+    pub notes: Vec<NoteReport>,
+}
+
+/// An imported assignment with the student's best result of every drill.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct AssignedWork {
+    pub assignment: Assignment,
+    pub results: Vec<Option<DrillReport>>,
+}
+
+/// The homework of the active profile.
+#[derive(Resource, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Homework {
+    pub work: Vec<AssignedWork>,
+}
+
+impl Homework {
+    pub fn load(profile: &Profile) -> Self {
+        let Some(contents) = storage::read(&profile.key(HOMEWORK_KEY)) else {
+            return default();
+        };
+        match ron::from_str(&contents) {
+            Ok(homework) => homework,
+            Err(err) => {
+                println!("Invalid homework, starting over: {}", err);
+                default()
+            }
+        }
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&profile.key(HOMEWORK_KEY), &contents));
+        if let Err(err) = result {
+            println!("Failed to save homework: {}", err);
+        }
+    }
+
+    /// Adds the assignment, importing it again replaces it and its results.
+    pub fn import(&mut self, assignment: Assignment) {
+        self.work.retain(|w| w.assignment.title != assignment.title);
+        self.work.push(AssignedWork {
+            results: vec![None; assignment.drills.len()],
+            assignment,
+        });
+    }
+
+    /// Keeps the report if it is the best one of its drill.
+    fn record(&mut self, drill: &Drill, report: DrillReport) {
+        for work in &mut self.work {
+            for (assigned, result) in work.assignment.drills.iter().zip(&mut work.results) {
+                if assigned.course != drill.course
+                    || assigned.seed != drill.seed
+                    || assigned.notes != drill.notes
+                {
+                    continue;
+                }
+                let report = DrillReport {
+                    passed: report.accuracy >= assigned.required_accuracy,
+                    ..report.clone()
+                };
+                if result.as_ref().is_none_or(|r| report.accuracy > r.accuracy) {
+                    *result = Some(report);
+                }
+            }
+        }
+    }
+}
+
+/// What the student sends back to the teacher.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct Report {
+    pub assignment: String,
+    pub teacher: String,
+    pub due: Option<String>,
+    pub student: String,
+    /// Date the student signed the report off, when the device knows it
+    pub signed_off: Option<String>,
+    pub drills: Vec<(Drill, Option<DrillReport>)>,
+}
+
+impl Report {
+    pub fn new(work: &AssignedWork, student: &str) -> Self {
+        let assignment = &work.assignment;
+        Report {
+            assignment: assignment.title.clone(),
+            teacher: assignment.teacher.clone(),
+            due: assignment.due.clone(),
+            student: student.to_string(),
+            signed_off: today(),
+            drills: assignment
+                .drills
+                .iter()
+                .cloned()
+                .zip(work.results.iter().cloned())
+                .collect(),
+        }
+    }
+
+    /// Hands the report to the student to send, returns what became of it.
+    fn export(&self) -> anyhow::Result<String> {
+        let file_name: String = format!("{} {}", self.assignment, self.student)
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        share::offer(&file_name, &ron::ser::to_string_pretty(self, default())?)
+    }
+}
+
+/// Clock of the running assigned round.
+#[derive(Resource, Default)]
+pub(super) struct HomeworkRound {
+    drill: Option<Drill>,
+    last_answer: Duration,
+    seconds: Vec<f32>,
+}
+
+/// Times the answers of assigned rounds and records them once finished.
+pub(super) fn homework_progress_system(
+    mut actions: EventReader<GameAction>,
+    mut answers: EventReader<NoteAnswered>,
+    mut round: ResMut<HomeworkRound>,
+    mut homework: ResMut<Homework>,
+    train: Res<TrainSequence>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    for action in actions.read() {
+        if let GameButton::Seeded {
+            course,
+            notes,
+            seed,
+        } = action.button
+        {
+            *round = HomeworkRound {
+                drill: Some(Drill {
+                    course,
+                    seed,
+                    notes,
+                    required_accuracy: 0.,
+                }),
+                last_answer: now,
+                seconds: vec![0.; notes],
+            };
+        }
+    }
+
+    for answer in answers.read() {
+        let Some(drill) = round.drill.clone() else {
+            continue;
+        };
+        if train.seed != drill.seed || train.retry || train.lesson.is_some() {
+            round.drill = None;
+            continue;
+        }
+        let elapsed = (now - round.last_answer).as_secs_f32();
+        round.last_answer = now;
+        if let Some(seconds) = round.seconds.get_mut(answer.index) {
+            *seconds += elapsed;
+        }
+        if train.next_key < train.notes.len() {
+            continue;
+        }
+
+        let notes: Vec<NoteReport> = train
+            .notes
+            .iter()
+            .zip(&round.seconds)
+            .map(|(note, &seconds)| NoteReport {
+                note: settings.naming.name_with_octave(note.pitch, false),
+                answer: note.answer_name(settings.naming).unwrap_or_default(),
+                correct: note.is_correct(),
+                attempts: note.attempts,
+                seconds,
+            })
+            .collect();
+        let correct = notes.iter().filter(|n| n.correct).count();
+        homework.record(
+            &drill,
+            DrillReport {
+                accuracy: correct as f32 / notes.len().max(1) as f32,
+                passed: false,
+                seconds: round.seconds.iter().sum(),
+                notes,
+            },
+        );
+        round.drill = None;
+    }
+}
+
+/// Imports the assignment files dropped on the window and those handed over
+/// by the page or the app opening them, once a profile is picked.
+pub(super) fn homework_import_system(
+    mut drops: EventReader<FileDragAndDrop>,
+    mut host: EventReader<HostCommand>,
+    mut pending: Local<Vec<(String, String)>>,
+    mut homework: ResMut<Homework>,
+    mut message: ResMut<HomeworkMessage>,
+    profile: Option<Res<Profile>>,
+) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else {
+            continue;
+        };
+        match std::fs::read_to_string(path_buf) {
+            Ok(contents) => pending.push((path_buf.display().to_string(), contents)),
+            Err(err) => message.0 = format!("Can't import {}: {}", path_buf.display(), err),
+        }
+    }
+    for command in host.read() {
+        if let HostCommand::ImportHomework(contents) = command {
+            pending.push(("the assignment".to_string(), contents.clone()));
+        }
+    }
+    if pending.is_empty() {
+        return;
+    }
+    if profile.is_none() {
+        const PICK_PROFILE: &str = "Pick a profile to import the homework";
+        if message.0 != PICK_PROFILE {
+            message.0 = PICK_PROFILE.to_string();
+        }
+        return;
+    }
+    for (source, contents) in pending.drain(..) {
+        message.0 = match Assignment::parse(&contents) {
+            Ok(assignment) => {
+                let message = format!("Imported {}", assignment.title);
+                homework.import(assignment);
+                message
+            }
+            Err(err) => format!("Can't import {}: {}", source, err),
+        };
+        println!("{}", message.0);
+    }
+}
+
+/// Imports the assignment file Android opened the app with, then the ones
+/// opened while it runs.
+#[cfg(target_os = "android")]
+pub(super) fn opened_assignment_system(
+    mut host: EventWriter<HostCommand>,
+    mut started: Local<bool>,
+) {
+    if !*started {
+        *started = true;
+        match share::opened_file() {
+            Ok(Some(contents)) => {
+                host.send(HostCommand::ImportHomework(contents));
+            }
+            Ok(None) => {}
+            Err(err) => println!("Can't read the opened file: {}", err),
+        }
+    }
+    for contents in share::handed_over_files() {
+        host.send(HostCommand::ImportHomework(contents));
+    }
+}
+
+/// Last import or export outcome, shown at the bottom of the homework list.
+#[derive(Resource)]
+pub(super) struct HomeworkMessage(pub String);
+
+impl Default for HomeworkMessage {
+    fn default() -> Self {
+        HomeworkMessage(IMPORT_HINT.to_string())
+    }
+}
+
+#[derive(Component)]
+pub(super) struct HomeworkScreen;
+
+#[derive(Component, Clone, Copy)]
+pub(super) enum HomeworkButton {
+    Start {
+        work: usize,
+        drill: usize,
+    },
+    /// Signs the report of an assignment off and exports it
+    Report(usize),
+    /// Asks the browser for an assignment file
+    #[cfg(target_arch = "wasm32")]
+    Import,
+    Back,
+}
+
+/// Builds the homework list, and rebuilds it whenever the homework or the
+/// message change.
+pub(super) fn homework_screen_system(
+    mut commands: Commands,
+    screens: Query<Entity, With<HomeworkScreen>>,
+    homework: Res<Homework>,
+    message: Res<HomeworkMessage>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let changed = homework.is_changed() || message.is_changed() || settings.is_changed();
+    if !screens.is_empty() && !changed {
+        return;
+    }
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    let style = |font_size: f32, color: Color| TextStyle {
+        font: font.clone(),
+        font_size,
+        color,
+    };
+    let size = Style {
+        width: Val::Px(520.0),
+        height: Val::Px(40.0),
+        ..default()
+    };
+    let today = today();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.),
+                    ..config.region_style()
+                },
+                background_color: palette.background.into(),
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            HomeworkScreen,
+            ScaledUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Homework",
+                style(40., palette.ink),
+            ));
+            for (work_index, work) in homework.work.iter().enumerate() {
+                let assignment = &work.assignment;
+                let mut title = assignment.title.clone();
+                if !assignment.teacher.is_empty() {
+                    title += &format!(" from {}", assignment.teacher);
+                }
+                if let Some(due) = &assignment.due {
+                    // ISO dates compare as strings
+                    let overdue = today.as_ref().is_some_and(|today| today > due);
+                    title += &format!(", due {}{}", due, if overdue { " (overdue)" } else { "" });
+                }
+                parent.spawn(TextBundle::from_section(title, style(24., palette.ink)));
+                for (drill_index, (drill, result)) in
+                    assignment.drills.iter().zip(&work.results).enumerate()
+                {
+                    let outcome = match result {
+                        Some(result) if result.passed => {
+                            format!("done, {:.0}%", result.accuracy * 100.)
+                        }
+                        Some(result) => format!("best {:.0}%, try again", result.accuracy * 100.),
+                        None => "to do".to_string(),
+                    };
+                    let label = format!(
+                        "{}, {} notes, {:.0}% needed: {}",
+                        drill.course.as_ref(),
+                        drill.notes,
+                        drill.required_accuracy * 100.,
+                        outcome
+                    );
+                    spawn_button(
+                        parent,
+                        HomeworkButton::Start {
+                            work: work_index,
+                            drill: drill_index,
+                        },
+                        size.clone(),
+                        TextBundle::from_section(label.clone(), style(18., palette.button_text)),
+                        format!("Start drill {}", label),
+                        palette,
+                    );
+                }
+                spawn_button(
+                    parent,
+                    HomeworkButton::Report(work_index),
+                    size.clone(),
+                    TextBundle::from_section(
+                        "Sign off and export the report",
+                        style(18., palette.button_text),
+                    ),
+                    format!("Sign off and export the report of {}", assignment.title),
+                    palette,
+                );
+            }
+            #[cfg(target_arch = "wasm32")]
+            spawn_button(
+                parent,
+                HomeworkButton::Import,
+                size.clone(),
+                TextBundle::from_section("Import an assignment", style(18., palette.button_text)),
+                "Import an assignment file".to_string(),
+                palette,
+            );
+            spawn_button(
+                parent,
+                HomeworkButton::Back,
+                size.clone(),
+                TextBundle::from_section("Back", style(18., palette.button_text)),
+                "Back to the trainer".to_string(),
+                palette,
+            );
+            parent.spawn(TextBundle::from_section(
+                message.0.clone(),
+                style(18., palette.ink),
+            ));
+        });
+}
+
+pub(super) fn homework_screen_cleanup(
+    mut commands: Commands,
+    screens: Query<Entity, With<HomeworkScreen>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type HomeworkButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d HomeworkButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn homework_button_system(
+    mut interaction_query: HomeworkButtonQuery,
+    homework: Res<Homework>,
+    mut message: ResMut<HomeworkMessage>,
+    profile: Option<Res<Profile>>,
+    settings: Res<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut actions: EventWriter<GameAction>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            HomeworkButton::Start { work, drill } => {
+                let drill = &homework.work[work].assignment.drills[drill];
+                // Played on the trainer screen, where it is recorded
+                let source = if keys.any_just_pressed(ACTIVATE_KEYS) {
+                    InputSource::Keyboard
+                } else {
+                    InputSource::Pointer
+                };
+                actions.send(GameAction {
+                    button: GameButton::Seeded {
+                        course: drill.course,
+                        notes: drill.notes,
+                        seed: drill.seed,
+                    },
+                    source,
+                });
+                next_screen.set(Screen::Trainer);
+            }
+            HomeworkButton::Report(work) => {
+                let student = profile.as_ref().map_or("", |p| p.name.as_str());
+                let report = Report::new(&homework.work[work], student);
+                message.0 = report
+                    .export()
+                    .unwrap_or_else(|err| format!("Export failed: {}", err));
+            }
+            #[cfg(target_arch = "wasm32")]
+            HomeworkButton::Import => crate::web::pick_homework(),
+            HomeworkButton::Back => next_screen.set(Screen::Trainer),
+        }
+    }
+}
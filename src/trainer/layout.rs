@@ -0,0 +1,298 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    window::{PrimaryWindow, WindowResized},
+};
+
+use super::{config::TrainerConfig, STAFF_NOTE_X, STAFF_SPACE_Y};
+
+/// Logical window size the landscape layout was designed for, every length
+/// in design units is multiplied by `StaffLayout::scale`.
+const LANDSCAPE_DESIGN_SIZE: Vec2 = Vec2::new(1200., 540.);
+const PORTRAIT_DESIGN_SIZE: Vec2 = Vec2::new(600., 1000.);
+
+/// Middle C position relative to the region center, in design units.
+const LANDSCAPE_STAFF_Y: f32 = 40.;
+const PORTRAIT_STAFF_Y: f32 = 25.;
+
+/// Room left and right of the staff for the brace and the final bar line.
+const STAFF_MARGIN_X: f32 = 32.;
+
+const LANDSCAPE_KEY_SIZE: Vec2 = Vec2::new(100., 130.);
+const PORTRAIT_KEY_SIZE: Vec2 = Vec2::new(80., 180.);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+/// World space placement of the grand staff in the trainer's region,
+/// recomputed whenever the primary window changes size. The 2d camera maps one
+/// world unit to one logical pixel, so HiDPI screens are handled by the window
+/// scale factor.
+#[derive(Resource, Clone, Copy, Debug)]
+pub(super) struct StaffLayout {
+    pub orientation: Orientation,
+    pub scale: f32,
+    pub staff_x: f32,
+    pub staff_width: f32,
+    pub staff_y: f32,
+    pub space_y: f32,
+    pub note_x: f32,
+}
+
+/// Marker for the answer keyboard buttons, resized when the orientation flips.
+#[derive(Component)]
+pub(super) struct AnswerKey;
+
+impl StaffLayout {
+    /// Fits the staff to a region of `size` around the world point `center`.
+    pub fn new(size: Vec2, center: Vec2) -> Self {
+        let (orientation, design_size, staff_y) = if size.x >= size.y {
+            (
+                Orientation::Landscape,
+                LANDSCAPE_DESIGN_SIZE,
+                LANDSCAPE_STAFF_Y,
+            )
+        } else {
+            (
+                Orientation::Portrait,
+                PORTRAIT_DESIGN_SIZE,
+                PORTRAIT_STAFF_Y,
+            )
+        };
+        let fit = size / design_size;
+        let scale = fit.x.min(fit.y).max(0.01);
+
+        StaffLayout {
+            orientation,
+            scale,
+            staff_x: center.x - size.x * 0.5 + STAFF_MARGIN_X * scale,
+            staff_width: size.x - STAFF_MARGIN_X * 2. * scale,
+            staff_y: center.y + staff_y * scale,
+            space_y: STAFF_SPACE_Y as f32 * scale,
+            note_x: STAFF_NOTE_X as f32 * scale,
+        }
+    }
+
+    pub fn index_to_x(&self, index: usize, note_space: f32) -> f32 {
+        self.staff_x + self.note_x + note_space * index as f32
+    }
+
+    pub fn pitch_to_y(&self, pitch: i32) -> f32 {
+        self.staff_y + pitch as f32 * self.space_y * 0.5
+    }
+
+    /// The line or space nearest to `y`, the inverse of `pitch_to_y`.
+    pub fn y_to_pitch(&self, y: f32) -> i32 {
+        ((y - self.staff_y) / (self.space_y * 0.5)).round() as i32
+    }
+
+    /// Horizontal distance between two neighbouring notes of a round.
+    pub fn note_space(&self, num_notes: usize) -> f32 {
+        let segments_f = 1.0f32.max(num_notes as f32 - 1.0);
+        (self.staff_width - self.note_x - 40. * self.scale) / segments_f
+    }
+
+    fn key_size(&self) -> Vec2 {
+        match self.orientation {
+            Orientation::Landscape => LANDSCAPE_KEY_SIZE,
+            Orientation::Portrait => PORTRAIT_KEY_SIZE,
+        }
+    }
+}
+
+impl Default for StaffLayout {
+    fn default() -> Self {
+        StaffLayout::new(LANDSCAPE_DESIGN_SIZE, Vec2::ZERO)
+    }
+}
+
+pub(super) fn layout_setup(
+    mut layout: ResMut<StaffLayout>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    config: Res<TrainerConfig>,
+) {
+    if let Ok(window) = windows.get_single() {
+        let (size, center) = config.world_region(window.size());
+        *layout = StaffLayout::new(size, center);
+    }
+}
+
+pub(super) fn layout_resize_system(
+    mut layout: ResMut<StaffLayout>,
+    mut resize_reader: EventReader<WindowResized>,
+    windows: Query<(), With<PrimaryWindow>>,
+    config: Res<TrainerConfig>,
+) {
+    for e in resize_reader.read() {
+        if windows.contains(e.window) {
+            let (size, center) = config.world_region(Vec2::new(e.width, e.height));
+            *layout = StaffLayout::new(size, center);
+        }
+    }
+}
+
+/// Switches the keyboard between the wide landscape keys and the tall portrait
+/// keys, in design units.
+pub(super) fn layout_apply_ui(
+    layout: Res<StaffLayout>,
+    mut keys: Query<&mut Style, With<AnswerKey>>,
+) {
+    if !layout.is_changed() {
+        return;
+    }
+    let key_size = layout.key_size();
+    for mut style in &mut keys {
+        style.width = Val::Px(key_size.x);
+        style.height = Val::Px(key_size.y);
+    }
+}
+
+/// Root of the trainer's nodes, which scale with the staff. The host's UI
+/// keeps its own size, colors and focus.
+#[derive(Component, Default)]
+pub(super) struct ScaledUi;
+
+/// The trainer's own UI nodes, the `ScaledUi` roots and their descendants.
+#[derive(SystemParam)]
+pub(super) struct TrainerNodes<'w, 's> {
+    roots: Query<'w, 's, Entity, With<ScaledUi>>,
+    children: Query<'w, 's, &'static Children>,
+}
+
+impl TrainerNodes<'_, '_> {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.roots
+            .iter()
+            .flat_map(|root| self.children.iter_descendants(root).chain([root]))
+    }
+}
+
+/// Pixel lengths and font sizes of a node as designed, and the scale they are
+/// shown at. Systems keep writing design sizes, a length that isn't the
+/// scaled design one is taken as the new design.
+#[derive(Component)]
+pub(super) struct Unscaled {
+    lengths: [Val; 25],
+    font_sizes: Vec<f32>,
+    scale: f32,
+}
+
+fn lengths(style: &mut Style) -> [&mut Val; 25] {
+    let Style {
+        left,
+        right,
+        top,
+        bottom,
+        width,
+        height,
+        min_width,
+        min_height,
+        max_width,
+        max_height,
+        flex_basis,
+        row_gap,
+        column_gap,
+        margin,
+        padding,
+        border,
+        ..
+    } = style;
+    [
+        left,
+        right,
+        top,
+        bottom,
+        width,
+        height,
+        min_width,
+        min_height,
+        max_width,
+        max_height,
+        flex_basis,
+        row_gap,
+        column_gap,
+        &mut margin.left,
+        &mut margin.right,
+        &mut margin.top,
+        &mut margin.bottom,
+        &mut padding.left,
+        &mut padding.right,
+        &mut padding.top,
+        &mut padding.bottom,
+        &mut border.left,
+        &mut border.right,
+        &mut border.top,
+        &mut border.bottom,
+    ]
+}
+
+fn scaled(length: Val, scale: f32) -> Val {
+    match length {
+        Val::Px(px) => Val::Px(px * scale),
+        other => other,
+    }
+}
+
+/// Scales the lengths and fonts of the trainer's nodes to the region, like
+/// `UiScale` would for the whole window.
+pub(super) fn ui_scale_system(
+    mut commands: Commands,
+    layout: Res<StaffLayout>,
+    trainer_nodes: TrainerNodes,
+    mut nodes: Query<(&mut Style, Option<&mut Text>, Option<&mut Unscaled>)>,
+) {
+    for entity in trainer_nodes.iter() {
+        let Ok((mut style, text, unscaled)) = nodes.get_mut(entity) else {
+            continue;
+        };
+        match unscaled {
+            Some(mut unscaled) => unscaled.apply(layout.scale, style, text),
+            None => {
+                let mut unscaled = Unscaled {
+                    lengths: lengths(style.bypass_change_detection()).map(|length| *length),
+                    font_sizes: vec![],
+                    scale: 1.,
+                };
+                unscaled.apply(layout.scale, style, text);
+                commands.entity(entity).insert(unscaled);
+            }
+        }
+    }
+}
+
+impl Unscaled {
+    /// Takes up what other systems wrote and shows the node at `scale`.
+    fn apply(&mut self, scale: f32, mut style: Mut<Style>, text: Option<Mut<Text>>) {
+        let shown = lengths(style.bypass_change_detection()).map(|length| *length);
+        for (design, length) in self.lengths.iter_mut().zip(shown) {
+            if length != scaled(*design, self.scale) {
+                *design = length;
+            }
+        }
+        for (i, design) in self.lengths.iter().enumerate() {
+            let length = scaled(*design, scale);
+            if shown[i] != length {
+                *lengths(&mut style)[i] = length;
+            }
+        }
+
+        if let Some(mut text) = text {
+            if text.sections.len() != self.font_sizes.len() {
+                self.font_sizes = vec![f32::NAN; text.sections.len()];
+            }
+            for (i, design) in self.font_sizes.iter_mut().enumerate() {
+                let shown = text.sections[i].style.font_size;
+                if shown != *design * self.scale {
+                    *design = shown;
+                }
+                if shown != *design * scale {
+                    text.sections[i].style.font_size = *design * scale;
+                }
+            }
+        }
+        self.scale = scale;
+    }
+}
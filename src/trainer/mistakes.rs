@@ -0,0 +1,130 @@
+//! Ghost note of the letter a wrong answer named, so students see how far off
+//! they were.
+
+use bevy::prelude::*;
+
+use super::{
+    config::{TrainerAssets, TrainerConfig, TrainerGizmos},
+    engraving::{ledger_lines, LEDGER_HALF_WIDTH},
+    labelled_notes,
+    layout::StaffLayout,
+    naming::nearest_pitch,
+    pointer::GHOST_ALPHA,
+    settings::{DrillMode, Settings},
+    TrainSequence, STAFF_ELEMENTS, STAFF_ELEM_IDX_NOTE_4, STAFF_MAX_NOTES,
+};
+
+/// Gap between the note head and the arrow to its ghost, in design units.
+const ARROW_X: f32 = 22.;
+
+/// Ghost note head and step count of the wrong answer to the note `index`.
+#[derive(Component)]
+pub(super) struct MistakeGhost {
+    index: usize,
+}
+
+pub(super) fn mistake_ghost_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+) {
+    let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_NOTE_4];
+    let texture = assets.load(asset_path);
+    let style = TextStyle {
+        font: assets.font(),
+        font_size: 20.0,
+        ..default()
+    };
+    for index in 0..STAFF_MAX_NOTES {
+        commands.spawn((
+            SpriteBundle {
+                texture: texture.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            MistakeGhost { index },
+            config.layers(),
+        ));
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section("", style.clone()),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            MistakeGhost { index },
+            config.layers(),
+        ));
+    }
+}
+
+/// The pitch the wrong answer to `index` named, when it is labelled.
+fn mistaken_pitch(train: &TrainSequence, settings: &Settings, index: usize) -> Option<i32> {
+    if train.mode != DrillMode::Read || index >= labelled_notes(train, settings.feedback) {
+        return None;
+    }
+    let note = &train.notes[index];
+    let key = note.pressed_key.filter(|_| !note.is_right())?;
+    Some(nearest_pitch(note.pitch, key))
+}
+
+pub(super) fn mistake_ghost_system(
+    mut gizmos: Gizmos<TrainerGizmos>,
+    mut ghosts: Query<(&MistakeGhost, &mut Sprite, &mut Transform, &mut Visibility)>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+) {
+    let palette = settings.theme.palette();
+    let color = palette.wrong.with_alpha(GHOST_ALPHA);
+    let note_space = layout.note_space(train.notes.len());
+    let half = LEDGER_HALF_WIDTH * layout.scale;
+    for (ghost, mut sprite, mut transform, mut visibility) in &mut ghosts {
+        let Some(pitch) = mistaken_pitch(&train, &settings, ghost.index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        sprite.color = color;
+        let x = layout.index_to_x(ghost.index, note_space);
+        *transform = Transform::from_xyz(x, layout.pitch_to_y(pitch), 0.6)
+            .with_scale(Vec3::splat(layout.scale));
+
+        for ledger in ledger_lines(pitch) {
+            let y = layout.pitch_to_y(ledger);
+            gizmos.line_2d(Vec2::new(x - half, y), Vec2::new(x + half, y), color);
+        }
+        let arrow_x = x + ARROW_X * layout.scale;
+        let from = layout.pitch_to_y(train.notes[ghost.index].pitch);
+        gizmos.arrow_2d(
+            Vec2::new(arrow_x, from),
+            Vec2::new(arrow_x, layout.pitch_to_y(pitch)),
+            palette.wrong,
+        );
+    }
+}
+
+/// Number of steps next to the arrow.
+pub(super) fn mistake_steps_system(
+    mut steps: Query<(&MistakeGhost, &mut Text, &mut Transform, &mut Visibility)>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+) {
+    let note_space = layout.note_space(train.notes.len());
+    for (ghost, mut text, mut transform, mut visibility) in &mut steps {
+        let Some(pitch) = mistaken_pitch(&train, &settings, ghost.index) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        *visibility = Visibility::Visible;
+        let from = train.notes[ghost.index].pitch;
+        let section = &mut text.sections[0];
+        section.value = (pitch - from).abs().to_string();
+        section.style.color = settings.theme.palette().wrong;
+        section.style.font_size = 20. * layout.scale;
+
+        let x = layout.index_to_x(ghost.index, note_space) + 2. * ARROW_X * layout.scale;
+        let y = (layout.pitch_to_y(from) + layout.pitch_to_y(pitch)) * 0.5;
+        *transform = Transform::from_xyz(x, y, 1.);
+    }
+}
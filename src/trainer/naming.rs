@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumIter};
+
+use super::{config::TrainerAssets, settings::Settings};
+
+/// Number of note names in an octave, C is pitch class 0.
+pub(super) const PITCH_CLASSES: usize = 7;
+
+/// Kana and hangul for the note names, Fira Sans only covers Latin script.
+/// Not shipped, see the README for making it.
+const CJK_FONT_PATH: &str = "fonts/NoteNamesCJK.otf";
+
+/// How notes are named on the answer keyboard and the staff labels. Answers
+/// are checked by pitch class, so every system judges the same way.
+#[derive(
+    Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize, EnumIter, AsRefStr,
+)]
+pub(super) enum NoteNaming {
+    #[default]
+    English,
+    Solfege,
+    German,
+    Japanese,
+    Korean,
+}
+
+pub(super) fn pitch_class(pitch: i32) -> usize {
+    pitch.rem_euclid(PITCH_CLASSES as i32) as usize
+}
+
+/// Scientific octave number, middle C (pitch 0) is C4.
+pub(super) fn octave(pitch: i32) -> i32 {
+    4 + pitch.div_euclid(PITCH_CLASSES as i32)
+}
+
+/// The pitch of class `key` nearest to `pitch`, in the same octave or the
+/// next one.
+pub(super) fn nearest_pitch(pitch: i32, key: usize) -> i32 {
+    let up = (key as i32 - pitch_class(pitch) as i32).rem_euclid(PITCH_CLASSES as i32);
+    if up > PITCH_CLASSES as i32 / 2 {
+        pitch + up - PITCH_CLASSES as i32
+    } else {
+        pitch + up
+    }
+}
+
+impl NoteNaming {
+    /// Japanese and Korean names are written in kana and hangul when `native`,
+    /// romanized otherwise.
+    pub fn names(self, native: bool) -> [&'static str; PITCH_CLASSES] {
+        match self {
+            NoteNaming::English => ["C", "D", "E", "F", "G", "A", "B"],
+            // Fixed do, Do is always C
+            NoteNaming::Solfege => ["Do", "Re", "Mi", "Fa", "Sol", "La", "Si"],
+            NoteNaming::German => ["C", "D", "E", "F", "G", "A", "H"],
+            NoteNaming::Japanese if native => ["ハ", "ニ", "ホ", "ヘ", "ト", "イ", "ロ"],
+            NoteNaming::Korean if native => ["다", "라", "마", "바", "사", "가", "나"],
+            NoteNaming::Japanese => ["Ha", "Ni", "Ho", "He", "To", "I", "Ro"],
+            NoteNaming::Korean => ["Da", "Ra", "Ma", "Ba", "Sa", "Ga", "Na"],
+        }
+    }
+
+    /// Written in kana or hangul, with the CJK font.
+    fn needs_cjk(self) -> bool {
+        matches!(self, NoteNaming::Japanese | NoteNaming::Korean)
+    }
+
+    pub fn name(self, pitch: i32, native: bool) -> &'static str {
+        self.names(native)[pitch_class(pitch)]
+    }
+
+    pub fn name_with_octave(self, pitch: i32, native: bool) -> String {
+        format!("{}{}", self.name(pitch, native), octave(pitch))
+    }
+
+    /// Short sample of the system shown on the button that switches it.
+    pub fn label(self, native: bool) -> String {
+        self.names(native)[..3].join(" ")
+    }
+}
+
+/// Text showing note names, drawn with the CJK font when they need it.
+#[derive(Component)]
+pub(super) struct NameLabel;
+
+/// The CJK font, loaded the first time a naming system needs it.
+#[derive(Resource, Default)]
+pub(super) struct NameFont {
+    handle: Option<Handle<Font>>,
+    /// Names may be written in their own script
+    pub loaded: bool,
+}
+
+pub(super) fn name_font_system(
+    mut name_font: ResMut<NameFont>,
+    mut events: EventReader<AssetEvent<Font>>,
+    mut labels: Query<(&mut Text, Ref<NameLabel>)>,
+    mut settings: ResMut<Settings>,
+    assets: TrainerAssets,
+) {
+    let naming = settings.naming;
+    if naming.needs_cjk() && name_font.handle.is_none() {
+        name_font.handle = Some(assets.load(CJK_FONT_PATH));
+    }
+    let Some(cjk) = name_font.handle.clone() else {
+        return;
+    };
+    let loaded = events
+        .read()
+        .any(|event| event.is_loaded_with_dependencies(&cjk));
+    if loaded {
+        name_font.loaded = true;
+        // Writes the names again, in their own script
+        settings.set_changed();
+    }
+    let font = match naming.needs_cjk() && name_font.loaded {
+        true => cjk,
+        false => assets.font(),
+    };
+    let refresh = loaded || settings.is_changed();
+    for (mut text, label) in &mut labels {
+        if refresh || label.is_added() {
+            for section in text.sections.iter_mut() {
+                section.style.font = font.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! The line or space under the mouse or a finger, the ghost note following it
+//! and the notes written or explored with it.
+
+use std::ops::RangeInclusive;
+
+use bevy::{audio::Pitch, prelude::*, sprite::Anchor, window::PrimaryWindow};
+
+use super::{
+    config::{TrainerAssets, TrainerCamera, TrainerConfig, TrainerGizmos},
+    engraving::{ledger_lines, LEDGER_HALF_WIDTH},
+    layout::StaffLayout,
+    naming::{NameFont, NameLabel},
+    recording::InputSource,
+    settings::{DrillMode, Settings},
+    sound, GameAction, GameButton, Screen, TrainSequence, STAFF_ELEMENTS, STAFF_ELEM_IDX_NOTE_4,
+};
+
+/// Pitches reachable with up to three ledger lines.
+pub(super) const POINTER_PITCHES: RangeInclusive<i32> = -16..=16;
+pub(super) const GHOST_ALPHA: f32 = 0.4;
+
+#[derive(Resource, Default)]
+pub(super) struct StaffPointer {
+    /// World position, unless the pointer is off the window or over a button
+    pub position: Option<Vec2>,
+    /// Line or space under the pointer, when it is on the staff
+    pub pitch: Option<i32>,
+    /// The mouse button or the finger was lifted this frame
+    pub released: bool,
+}
+
+/// Faded note head showing where a note would go.
+#[derive(Component)]
+pub(super) struct GhostNote;
+
+/// Name of the ghost note while exploring.
+#[derive(Component)]
+pub(super) struct GhostLabel;
+
+pub(super) fn ghost_note_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+) {
+    let (_, asset_path) = STAFF_ELEMENTS[STAFF_ELEM_IDX_NOTE_4];
+    commands.spawn((
+        SpriteBundle {
+            texture: assets.load(asset_path),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        GhostNote,
+        config.layers(),
+    ));
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font: assets.font(),
+                    font_size: 30.0,
+                    ..default()
+                },
+            ),
+            text_anchor: Anchor::CenterLeft,
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        GhostLabel,
+        NameLabel,
+        config.layers(),
+    ));
+}
+
+pub(super) fn pointer_system(
+    mut pointer: ResMut<StaffPointer>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<TrainerCamera>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    touches: Res<Touches>,
+    buttons: Query<&Interaction>,
+    layout: Res<StaffLayout>,
+) {
+    // A lifted finger still counts, it is where the note is written
+    let touch = touches
+        .iter_just_released()
+        .chain(touches.iter())
+        .next()
+        .map(|touch| touch.position());
+    let cursor = touch.or_else(|| windows.get_single().ok()?.cursor_position());
+    let over_button = buttons.iter().any(|i| *i != Interaction::None);
+
+    let position = cursor.filter(|_| !over_button).and_then(|cursor| {
+        let (camera, transform) = cameras.get_single().ok()?;
+        camera.viewport_to_world_2d(transform, cursor)
+    });
+    let on_staff = |p: &Vec2| (layout.staff_x..layout.staff_x + layout.staff_width).contains(&p.x);
+    pointer.pitch = position
+        .filter(on_staff)
+        .map(|p| layout.y_to_pitch(p.y))
+        .filter(|pitch| POINTER_PITCHES.contains(pitch));
+    pointer.position = position;
+    pointer.released = mouse.just_released(MouseButton::Left) || touches.any_just_released();
+}
+
+/// Writes the note where the student clicked or tapped.
+pub(super) fn pointer_place_system(
+    pointer: Res<StaffPointer>,
+    train: Res<TrainSequence>,
+    mut actions: EventWriter<GameAction>,
+) {
+    let writing = matches!(train.mode, DrillMode::Write | DrillMode::WriteWithOctave);
+    if !writing || !pointer.released {
+        return;
+    }
+    if let Some(pitch) = pointer.pitch {
+        actions.send(GameAction {
+            button: GameButton::Place(pitch),
+            source: InputSource::Pointer,
+        });
+    }
+}
+
+/// Column and pitch of the ghost note, if it is shown.
+fn ghost_position(
+    pointer: &StaffPointer,
+    train: &TrainSequence,
+    layout: &StaffLayout,
+    screen: &Screen,
+) -> Option<(f32, i32)> {
+    let (pitch, position) = pointer.pitch.zip(pointer.position)?;
+    if *screen != Screen::Trainer {
+        return None;
+    }
+    match train.mode {
+        DrillMode::Read => None,
+        DrillMode::Write | DrillMode::WriteWithOctave => {
+            let note_space = layout.note_space(train.notes.len());
+            (train.next_key < train.notes.len())
+                .then(|| (layout.index_to_x(train.next_key, note_space), pitch))
+        }
+        DrillMode::Explore => Some((position.x, pitch)),
+    }
+}
+
+/// Shows the note about to be written at the cursor column, or the note under
+/// the pointer while exploring.
+pub(super) fn ghost_note_system(
+    mut gizmos: Gizmos<TrainerGizmos>,
+    mut ghosts: Query<(&mut Sprite, &mut Transform, &mut Visibility), With<GhostNote>>,
+    pointer: Res<StaffPointer>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+    screen: Res<State<Screen>>,
+) {
+    let Ok((mut sprite, mut transform, mut visibility)) = ghosts.get_single_mut() else {
+        return;
+    };
+    let Some((x, pitch)) = ghost_position(&pointer, &train, &layout, screen.get()) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let color = settings.theme.palette().ink.with_alpha(GHOST_ALPHA);
+    *visibility = Visibility::Visible;
+    sprite.color = color;
+    *transform =
+        Transform::from_xyz(x, layout.pitch_to_y(pitch), 0.6).with_scale(Vec3::splat(layout.scale));
+    let half = LEDGER_HALF_WIDTH * layout.scale;
+    for ledger in ledger_lines(pitch) {
+        let y = layout.pitch_to_y(ledger);
+        gizmos.line_2d(Vec2::new(x - half, y), Vec2::new(x + half, y), color);
+    }
+}
+
+/// Names the explored note next to the ghost note.
+pub(super) fn ghost_label_system(
+    mut labels: Query<(&mut Text, &mut Transform, &mut Visibility), With<GhostLabel>>,
+    pointer: Res<StaffPointer>,
+    train: Res<TrainSequence>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+    screen: Res<State<Screen>>,
+) {
+    let Ok((mut text, mut transform, mut visibility)) = labels.get_single_mut() else {
+        return;
+    };
+    let ghost = ghost_position(&pointer, &train, &layout, screen.get())
+        .filter(|_| train.mode == DrillMode::Explore);
+    let Some((x, pitch)) = ghost else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    *visibility = Visibility::Visible;
+    let section = &mut text.sections[0];
+    section.value = settings.naming.name_with_octave(pitch, name_font.loaded);
+    section.style.color = settings.theme.palette().cursor;
+    section.style.font_size = 30. * layout.scale;
+    let x = x + (LEDGER_HALF_WIDTH + 4.) * layout.scale;
+    *transform = Transform::from_xyz(x, layout.pitch_to_y(pitch), 1.);
+}
+
+/// Plays the note under the pointer whenever it moves to another line or
+/// space, or is tapped, while exploring.
+pub(super) fn explore_sound_system(
+    mut commands: Commands,
+    mut last: Local<Option<i32>>,
+    pointer: Res<StaffPointer>,
+    train: Res<TrainSequence>,
+    settings: Res<Settings>,
+    screen: Res<State<Screen>>,
+    pitches: Option<ResMut<Assets<Pitch>>>,
+) {
+    let exploring = train.mode == DrillMode::Explore && *screen.get() == Screen::Trainer;
+    let pitch = pointer.pitch.filter(|_| exploring);
+    let moved = pitch != *last;
+    *last = pitch;
+    let (Some(pitch), Some(mut pitches)) = (pitch, pitches) else {
+        return;
+    };
+    if settings.sound && (moved || pointer.released) {
+        sound::play_pitch(&mut commands, &mut pitches, pitch);
+    }
+}
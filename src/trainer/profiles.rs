@@ -0,0 +1,612 @@
+//! Students sharing a device each get a profile. Everything the trainer keeps
+//! across sessions is stored under the key prefix of the active one.
+
+use bevy::{
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::{TrainerAssets, TrainerConfig},
+    curriculum::{Curriculum, Progress, PROGRESS_KEY},
+    homework::{Homework, HomeworkRound, HOMEWORK_KEY},
+    layout::ScaledUi,
+    recording::{Recorder, Replay, LAST_SESSION_KEY},
+    settings::{Settings, SETTINGS_KEY},
+    stats::{TrainStats, STATS_KEY},
+    versus::{spawn_button, Versus},
+    xapi::ACCOUNT_KEY,
+    LaunchOptions, Screen, TrainSequence,
+};
+use crate::{share, storage};
+
+const PROFILES_KEY: &str = "profiles";
+/// Everything stored per profile
+const PROFILE_KEYS: [&str; 6] = [
+    SETTINGS_KEY,
+    PROGRESS_KEY,
+    STATS_KEY,
+    HOMEWORK_KEY,
+    LAST_SESSION_KEY,
+    ACCOUNT_KEY,
+];
+const DEFAULT_NAME: &str = "Student";
+const MAX_NAME_LEN: usize = 24;
+
+/// The student practising, present once one is picked.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(crate) struct Profile {
+    /// Names the storage keys, so renaming keeps the data
+    id: u32,
+    pub name: String,
+}
+
+impl Profile {
+    /// Storage key of `key` for this profile.
+    pub fn key(&self, key: &str) -> String {
+        format!("profile_{}/{}", self.id, key)
+    }
+}
+
+/// Every profile on the device, stored outside of any profile.
+#[derive(Resource, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Profiles {
+    profiles: Vec<Profile>,
+    /// Profile picked last time
+    last: Option<u32>,
+    next_id: u32,
+}
+
+impl Profiles {
+    pub fn load() -> Self {
+        let Some(contents) = storage::read(PROFILES_KEY) else {
+            return default();
+        };
+        match ron::from_str::<Profiles>(&contents) {
+            Ok(profiles) => profiles,
+            Err(err) => {
+                println!("Invalid profile list, starting over: {}", err);
+                default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(PROFILES_KEY, &contents));
+        if let Err(err) = result {
+            println!("Failed to save the profile list: {}", err);
+        }
+    }
+
+    pub fn last(&self) -> Option<&Profile> {
+        self.get(self.last?)
+    }
+
+    fn get(&self, id: u32) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.id == id)
+    }
+
+    /// The profile named `name`, or the one picked last without a name.
+    pub(crate) fn find(&self, name: Option<&str>) -> anyhow::Result<&Profile> {
+        match name {
+            Some(name) => self
+                .profiles
+                .iter()
+                .find(|p| p.name == name)
+                .ok_or_else(|| anyhow::anyhow!("no profile named {}", name)),
+            None => self
+                .last()
+                .ok_or_else(|| anyhow::anyhow!("no profile was picked yet")),
+        }
+    }
+
+    /// The profile named `name`, created when there is none.
+    fn find_or_create(&mut self, name: &str) -> Profile {
+        if let Ok(profile) = self.find(Some(name)) {
+            return profile.clone();
+        }
+        let mut profile = self.create();
+        self.rename(profile.id, name).expect("the name is free");
+        profile.name = name.to_string();
+        profile
+    }
+
+    /// Adds a profile with a name not taken yet.
+    pub(super) fn create(&mut self) -> Profile {
+        let name = (1..)
+            .map(|n| match n {
+                1 => DEFAULT_NAME.to_string(),
+                n => format!("{} {}", DEFAULT_NAME, n),
+            })
+            .find(|name| self.profiles.iter().all(|p| p.name != *name))
+            .unwrap();
+        let profile = Profile {
+            id: self.next_id,
+            name,
+        };
+        self.next_id += 1;
+        self.profiles.push(profile.clone());
+        profile
+    }
+
+    /// Names pick the profile on the command line and name its exports, so
+    /// no two profiles share one.
+    fn rename(&mut self, id: u32, name: &str) -> anyhow::Result<()> {
+        if self.profiles.iter().any(|p| p.id != id && p.name == name) {
+            anyhow::bail!("{} is taken", name);
+        }
+        if let Some(profile) = self.profiles.iter_mut().find(|p| p.id == id) {
+            profile.name = name.to_string();
+        }
+        Ok(())
+    }
+
+    /// Removes the profile and everything stored under it.
+    fn delete(&mut self, id: u32) {
+        let Some(index) = self.profiles.iter().position(|p| p.id == id) else {
+            return;
+        };
+        let profile = self.profiles.remove(index);
+        for key in PROFILE_KEYS {
+            if let Err(err) = storage::remove(&profile.key(key)) {
+                println!("Failed to delete {} of {}: {}", key, profile.name, err);
+            }
+        }
+        if self.last == Some(id) {
+            self.last = None;
+        }
+    }
+}
+
+/// One file with everything stored for a profile, to move it to another
+/// device or keep a copy.
+#[derive(Serialize)]
+struct ProfileExport {
+    name: String,
+    settings: Settings,
+    progress: Progress,
+    stats: TrainStats,
+    homework: Homework,
+}
+
+/// Everything stored for the profile, as RON.
+pub(crate) fn export_contents(profile: &Profile) -> anyhow::Result<String> {
+    let export = ProfileExport {
+        name: profile.name.clone(),
+        settings: Settings::load(profile),
+        progress: Progress::load(profile),
+        stats: TrainStats::load(profile),
+        homework: Homework::load(profile),
+    };
+    Ok(ron::ser::to_string_pretty(&export, default())?)
+}
+
+/// Hands the export over like the homework reports, returns what became of it.
+fn export(profile: &Profile) -> anyhow::Result<String> {
+    let file_name: String = profile
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    share::offer(&file_name, &export_contents(profile)?)
+}
+
+/// Loads everything of the profile and starts a new session with it.
+fn activate(commands: &mut Commands, profile: Profile) {
+    println!("Practising as {}", profile.name);
+    let seed = rand::random();
+    let mut train = TrainSequence::new(seed);
+    train.gen_demo();
+    commands.insert_resource(train);
+    commands.insert_resource(Recorder::new(seed));
+    commands.remove_resource::<Versus>();
+    commands.insert_resource(Settings::load(&profile));
+    commands.insert_resource(Progress::load(&profile));
+    commands.insert_resource(TrainStats::load(&profile));
+    commands.insert_resource(Homework::load(&profile));
+    commands.insert_resource(HomeworkRound::default());
+    commands.insert_resource(Curriculum::default());
+    commands.insert_resource(profile);
+}
+
+/// Shows the profile picker when the app starts without a profile, unless
+/// one is named on the command line. A replay doesn't need one.
+pub(super) fn profile_startup(
+    mut commands: Commands,
+    mut profiles: ResMut<Profiles>,
+    profile: Option<Res<Profile>>,
+    replay: Option<Res<Replay>>,
+    launch: Option<Res<LaunchOptions>>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    if profile.is_some() || replay.is_some() {
+        return;
+    }
+    match launch.as_ref().and_then(|launch| launch.profile.as_deref()) {
+        Some(name) => {
+            let profile = profiles.find_or_create(name);
+            profiles.last = Some(profile.id);
+            profiles.save();
+            activate(&mut commands, profile);
+        }
+        None => next_screen.set(Screen::Profiles),
+    }
+}
+
+/// Saves whatever changed to the active profile.
+pub(super) fn profile_save_system(
+    profile: Res<Profile>,
+    settings: Res<Settings>,
+    progress: Res<Progress>,
+    stats: Res<TrainStats>,
+    homework: Res<Homework>,
+) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save(&profile);
+    }
+    if progress.is_changed() && !progress.is_added() {
+        progress.save(&profile);
+    }
+    if stats.is_changed() && !stats.is_added() {
+        stats.save(&profile);
+    }
+    if homework.is_changed() && !homework.is_added() {
+        homework.save(&profile);
+    }
+}
+
+/// State of the profile screen.
+#[derive(Resource, Default)]
+pub(super) struct ProfileEditor {
+    /// Profile being renamed and the name typed so far
+    renaming: Option<(u32, String)>,
+    /// Profile whose delete button was pressed once
+    confirm_delete: Option<u32>,
+    /// Outcome of the last export
+    message: String,
+}
+
+impl ProfileEditor {
+    fn finish_rename(
+        &mut self,
+        commands: &mut Commands,
+        profiles: &mut Profiles,
+        active: Option<&Profile>,
+    ) {
+        let Some((id, name)) = self.renaming.take() else {
+            return;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            return;
+        }
+        if let Err(err) = profiles.rename(id, name) {
+            self.message = err.to_string();
+            return;
+        }
+        profiles.save();
+        if let Some(active) = active.filter(|p| p.id == id) {
+            commands.insert_resource(Profile {
+                name: name.to_string(),
+                ..active.clone()
+            });
+        }
+    }
+}
+
+/// Run condition keeping typed names away from the keyboard navigation.
+pub(super) fn not_typing(editor: Res<ProfileEditor>) -> bool {
+    editor.renaming.is_none()
+}
+
+#[derive(Component)]
+pub(super) struct ProfileScreen;
+
+#[derive(Component, Clone, Copy)]
+pub(super) enum ProfileButton {
+    Select(u32),
+    /// Starts typing a new name, or confirms it
+    Rename(u32),
+    /// Asks for confirmation, then deletes
+    Delete(u32),
+    Export(u32),
+    New,
+    Back,
+}
+
+/// Builds the profile screen, and rebuilds it whenever the profiles or the
+/// editor change.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn profile_screen_system(
+    mut commands: Commands,
+    screens: Query<Entity, With<ProfileScreen>>,
+    profiles: Res<Profiles>,
+    editor: Res<ProfileEditor>,
+    active: Option<Res<Profile>>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let changed = profiles.is_changed() || editor.is_changed() || settings.is_changed();
+    if !screens.is_empty() && !changed {
+        return;
+    }
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    let text = |value: String, font_size: f32| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: palette.button_text,
+            },
+        )
+    };
+    let size = |width: f32| Style {
+        width: Val::Px(width),
+        height: Val::Px(44.0),
+        ..default()
+    };
+    let active_id = active.as_ref().map(|p| p.id);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.),
+                    ..config.region_style()
+                },
+                background_color: palette.background.into(),
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            ProfileScreen,
+            ScaledUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Who is practising?",
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 40.0,
+                    color: palette.ink,
+                },
+            ));
+            for profile in &profiles.profiles {
+                let id = profile.id;
+                let renaming = editor.renaming.as_ref().filter(|(r, _)| *r == id);
+                let name = match renaming {
+                    Some((_, typed)) => format!("{}_", typed),
+                    None if active_id == Some(id) => format!("{} (current)", profile.name),
+                    None => profile.name.clone(),
+                };
+                let (rename, rename_name) = match renaming {
+                    Some(_) => (
+                        "Done",
+                        format!("Keep the name {}", name.trim_end_matches('_')),
+                    ),
+                    None => ("Rename", format!("Rename {}", profile.name)),
+                };
+                let (delete, delete_name) = if editor.confirm_delete == Some(id) {
+                    (
+                        "Really delete?",
+                        format!("Confirm deleting {} and all its data", profile.name),
+                    )
+                } else {
+                    ("Delete", format!("Delete {}", profile.name))
+                };
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        let buttons = [
+                            (
+                                ProfileButton::Select(id),
+                                size(300.),
+                                name.clone(),
+                                format!("Practise as {}", profile.name),
+                            ),
+                            (
+                                ProfileButton::Rename(id),
+                                size(120.),
+                                rename.to_string(),
+                                rename_name,
+                            ),
+                            (
+                                ProfileButton::Delete(id),
+                                size(150.),
+                                delete.to_string(),
+                                delete_name,
+                            ),
+                            (
+                                ProfileButton::Export(id),
+                                size(120.),
+                                "Export".to_string(),
+                                format!("Export {}", profile.name),
+                            ),
+                        ];
+                        for (button, style, label, accessible_name) in buttons {
+                            spawn_button(
+                                row,
+                                button,
+                                style,
+                                text(label, 20.),
+                                accessible_name,
+                                palette,
+                            );
+                        }
+                    });
+            }
+            spawn_button(
+                parent,
+                ProfileButton::New,
+                size(300.),
+                text("New profile".to_string(), 20.),
+                "Create a new profile".to_string(),
+                palette,
+            );
+            if active_id.is_some() {
+                spawn_button(
+                    parent,
+                    ProfileButton::Back,
+                    size(300.),
+                    text("Back".to_string(), 20.),
+                    "Back to the trainer".to_string(),
+                    palette,
+                );
+            }
+            parent.spawn(TextBundle::from_section(
+                editor.message.clone(),
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 18.0,
+                    color: palette.ink,
+                },
+            ));
+        });
+}
+
+pub(super) fn profile_screen_cleanup(
+    mut commands: Commands,
+    screens: Query<Entity, With<ProfileScreen>>,
+    mut editor: ResMut<ProfileEditor>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+    *editor = default();
+}
+
+type ProfileButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d ProfileButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+pub(super) fn profile_button_system(
+    mut commands: Commands,
+    mut interaction_query: ProfileButtonQuery,
+    mut profiles: ResMut<Profiles>,
+    mut editor: ResMut<ProfileEditor>,
+    active: Option<Res<Profile>>,
+    settings: Res<Settings>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let confirm_delete = editor.confirm_delete.take();
+        match *button {
+            ProfileButton::Select(id) => {
+                editor.finish_rename(&mut commands, &mut profiles, active.as_deref());
+                let Some(profile) = profiles.get(id).cloned() else {
+                    continue;
+                };
+                profiles.last = Some(id);
+                profiles.save();
+                activate(&mut commands, profile);
+                next_screen.set(Screen::Trainer);
+            }
+            ProfileButton::Rename(id) => {
+                let renaming = editor.renaming.as_ref().map(|(r, _)| *r);
+                editor.finish_rename(&mut commands, &mut profiles, active.as_deref());
+                if renaming != Some(id) {
+                    if let Some(profile) = profiles.get(id) {
+                        editor.renaming = Some((id, profile.name.clone()));
+                    }
+                }
+            }
+            ProfileButton::Delete(id) if confirm_delete == Some(id) => {
+                editor.renaming = None;
+                profiles.delete(id);
+                profiles.save();
+                if active.as_ref().is_some_and(|p| p.id == id) {
+                    commands.remove_resource::<Profile>();
+                }
+            }
+            ProfileButton::Delete(id) => editor.confirm_delete = Some(id),
+            ProfileButton::Export(id) => {
+                let Some(profile) = profiles.get(id) else {
+                    continue;
+                };
+                editor.message = match export(profile) {
+                    Ok(message) => message,
+                    Err(err) => format!("Export failed: {}", err),
+                };
+            }
+            ProfileButton::New => {
+                editor.finish_rename(&mut commands, &mut profiles, active.as_deref());
+                let profile = profiles.create();
+                profiles.save();
+                editor.renaming = Some((profile.id, profile.name));
+            }
+            ProfileButton::Back => {
+                editor.finish_rename(&mut commands, &mut profiles, active.as_deref());
+                next_screen.set(Screen::Trainer);
+            }
+        }
+    }
+}
+
+/// Types the name of the profile being renamed, Enter keeps it and Escape
+/// drops it.
+pub(super) fn profile_name_input_system(
+    mut commands: Commands,
+    mut keys: EventReader<KeyboardInput>,
+    mut profiles: ResMut<Profiles>,
+    mut editor: ResMut<ProfileEditor>,
+    active: Option<Res<Profile>>,
+) {
+    for key in keys.read() {
+        if key.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some((_, name)) = editor.renaming.as_mut() else {
+            continue;
+        };
+        match &key.logical_key {
+            Key::Character(typed) => {
+                let typed = typed.chars().filter(|c| !c.is_control());
+                name.extend(typed.take(MAX_NAME_LEN.saturating_sub(name.chars().count())));
+            }
+            Key::Space if name.chars().count() < MAX_NAME_LEN => name.push(' '),
+            Key::Backspace => {
+                name.pop();
+            }
+            Key::Enter => editor.finish_rename(&mut commands, &mut profiles, active.as_deref()),
+            Key::Escape => editor.renaming = None,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Records the inputs of a session so it can be replayed exactly. Every round
+//! seed is drawn from the session seed, so the seed and the timed inputs are
+//! all a replay needs.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    embed::Paused,
+    profiles::{Profile, Profiles},
+    settings::Settings,
+    versus::VersusMode,
+    GameAction, GameButton, TrainSequence,
+};
+use crate::storage;
+
+pub(super) const LAST_SESSION_KEY: &str = "last_session";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(super) enum InputSource {
+    /// Mouse or touch
+    Pointer,
+    /// A focused button pressed with Enter or Space
+    Keyboard,
+    Gamepad,
+    /// The page or command line hosting the trainer
+    Host,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) enum RecordedInput {
+    Button {
+        button: GameButton,
+        source: InputSource,
+    },
+    /// Settings in effect from then on, the first one is recorded at startup
+    Settings(Settings),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct RecordedEvent {
+    /// Seconds since the start of the session
+    pub time: f32,
+    pub input: RecordedInput,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Recording {
+    pub(super) seed: u64,
+    pub(super) events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(contents)?)
+    }
+}
+
+/// Collects the inputs of the running session.
+#[derive(Resource)]
+pub(super) struct Recorder {
+    recording: Recording,
+    /// Event times count from the first frame recorded
+    started: Option<Duration>,
+}
+
+/// Plays a recording back instead of taking live input.
+#[derive(Resource)]
+pub(crate) struct Replay {
+    recording: Recording,
+    next: usize,
+    started: Option<Duration>,
+}
+
+impl Recorder {
+    pub fn new(seed: u64) -> Self {
+        Recorder {
+            recording: Recording {
+                seed,
+                events: vec![],
+            },
+            started: None,
+        }
+    }
+
+    fn save(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(&self.recording, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&profile.key(LAST_SESSION_KEY), &contents));
+        if let Err(err) = result {
+            println!("Failed to save the session recording: {}", err);
+        }
+    }
+}
+
+/// The recording of the last session of the profile picked last.
+pub(crate) fn read_last_session() -> Option<String> {
+    let profiles = Profiles::load();
+    storage::read(&profiles.last()?.key(LAST_SESSION_KEY))
+}
+
+impl Replay {
+    pub fn new(recording: Recording) -> Self {
+        Replay {
+            recording,
+            next: 0,
+            started: None,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.recording.seed
+    }
+}
+
+pub(super) fn recording_system(
+    mut recorder: ResMut<Recorder>,
+    mut actions: EventReader<GameAction>,
+    settings: Res<Settings>,
+    train: Res<TrainSequence>,
+    profile: Option<Res<Profile>>,
+    paused: Option<Res<Paused>>,
+    time: Res<Time>,
+) {
+    let started = *recorder.started.get_or_insert(time.elapsed());
+    let now = (time.elapsed() - started).as_secs_f32();
+    if settings.is_changed() {
+        recorder.recording.events.push(RecordedEvent {
+            time: now,
+            input: RecordedInput::Settings(settings.clone()),
+        });
+    }
+    // Ignored while paused
+    for action in actions.read().filter(|_| paused.is_none()) {
+        recorder.recording.events.push(RecordedEvent {
+            time: now,
+            input: RecordedInput::Button {
+                button: action.button,
+                source: action.source,
+            },
+        });
+    }
+
+    // Keep the file up to date at the end of every round
+    if train.is_changed() && !train.notes.is_empty() && train.next_key == train.notes.len() {
+        if let Some(profile) = &profile {
+            recorder.save(profile);
+        }
+    }
+}
+
+/// Feeds the recorded inputs to the trainer at their original times.
+pub(super) fn replay_system(
+    mut replay: ResMut<Replay>,
+    mut actions: EventWriter<GameAction>,
+    mut settings: ResMut<Settings>,
+    time: Res<Time>,
+) {
+    let started = *replay.started.get_or_insert(time.elapsed());
+    let now = (time.elapsed() - started).as_secs_f32();
+    while let Some(event) = replay.recording.events.get(replay.next) {
+        if event.time > now {
+            break;
+        }
+        match &event.input {
+            // Other screens can't be replayed, the settings they change are
+            // recorded on their own
+            RecordedInput::Button {
+                button:
+                    GameButton::Settings
+                    | GameButton::Lessons
+                    | GameButton::Profiles
+                    | GameButton::Homework
+                    | GameButton::Versus(VersusMode::Race),
+                ..
+            } => {}
+            RecordedInput::Button { button, source } => {
+                actions.send(GameAction {
+                    button: *button,
+                    source: *source,
+                });
+            }
+            RecordedInput::Settings(recorded) => *settings = recorded.clone(),
+        }
+        replay.next += 1;
+    }
+}
@@ -0,0 +1,134 @@
+//! Course scripts are Rhai, see <https://rhai.rs/book>. Scripts define
+//! functions, statements outside of them aren't run:
+//!
+//! ```text
+//! // Any note of the treble staff, the comments at the top describe it
+//! fn next(history) {
+//!     rand(0, 12)
+//! }
+//!
+//! // Optional, the note's letter is enough by default
+//! fn check(pitch, key, placed) {
+//!     key == pitch_class(pitch)
+//! }
+//! ```
+//!
+//! Besides Rhai's own functions, scripts have `rand(low, high)` with both
+//! ends included, `chance(percent)` and `pick(array)` drawing from the round's
+//! random generator, `min`, `max`, `clamp` and `pitch_class`.
+//!
+//! Scripts can't reach files or modules, and the engine's limits stop deep
+//! nesting, recursion and scripts stuck in a loop.
+
+use std::sync::{Arc, Mutex};
+
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rhai::{
+    module_resolvers::DummyModuleResolver, Array, CallFnOptions, Dynamic, Engine, EvalAltResult,
+    Scope, AST, INT,
+};
+
+/// Operations of a single call, before giving up on a script stuck in a loop.
+const MAX_OPERATIONS: u64 = 100_000;
+/// Nesting of expressions at the top level and in functions.
+const MAX_EXPR_DEPTH: usize = 64;
+const MAX_FUNCTION_EXPR_DEPTH: usize = 32;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_SIZE: usize = 10_000;
+
+type RhaiResult<T> = Result<T, Box<EvalAltResult>>;
+
+pub(super) struct Script {
+    engine: Engine,
+    ast: AST,
+    /// Generator of the running call, drawn from the round's
+    rng: Arc<Mutex<ChaCha8Rng>>,
+    /// Calls take turns, they share the generator
+    calls: Mutex<()>,
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script").finish_non_exhaustive()
+    }
+}
+
+impl Script {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let rng = Arc::new(Mutex::new(ChaCha8Rng::seed_from_u64(0)));
+        let engine = engine(&rng);
+        let ast = engine.compile(source)?;
+        Ok(Script {
+            engine,
+            ast,
+            rng,
+            calls: Mutex::new(()),
+        })
+    }
+
+    /// Parameter count of the function `name`, if the script defines it.
+    pub fn arity(&self, name: &str) -> Option<usize> {
+        self.ast
+            .iter_functions()
+            .find(|function| function.name == name)
+            .map(|function| function.params.len())
+    }
+
+    pub fn call(
+        &self,
+        name: &str,
+        args: Vec<Dynamic>,
+        rng: &mut dyn RngCore,
+    ) -> anyhow::Result<Dynamic> {
+        let _turn = self.calls.lock().unwrap();
+        *self.rng.lock().unwrap() = ChaCha8Rng::seed_from_u64(rng.next_u64());
+        let options = CallFnOptions::new().eval_ast(false);
+        let value =
+            self.engine
+                .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)?;
+        Ok(value)
+    }
+}
+
+fn engine(rng: &Arc<Mutex<ChaCha8Rng>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(DummyModuleResolver::new())
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_expr_depths(MAX_EXPR_DEPTH, MAX_FUNCTION_EXPR_DEPTH)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_SIZE)
+        .set_max_array_size(MAX_SIZE)
+        .set_max_map_size(MAX_SIZE)
+        .disable_symbol("eval")
+        .on_print(|text| println!("Course script: {}", text))
+        .on_debug(|text, _, position| println!("Course script {}: {}", position, text));
+
+    let draw = rng.clone();
+    engine.register_fn("rand", move |low: INT, high: INT| -> RhaiResult<INT> {
+        if low > high {
+            return Err(format!("rand({}, {}) has nothing to draw", low, high).into());
+        }
+        Ok(draw.lock().unwrap().gen_range(low..=high))
+    });
+    let draw = rng.clone();
+    engine.register_fn("chance", move |percent: INT| {
+        draw.lock().unwrap().gen_range(0..100) < percent
+    });
+    let draw = rng.clone();
+    engine.register_fn("pick", move |values: Array| -> RhaiResult<Dynamic> {
+        values
+            .choose(&mut *draw.lock().unwrap())
+            .cloned()
+            .ok_or_else(|| "pick from an empty array".into())
+    });
+    engine
+        .register_fn("min", |a: INT, b: INT| a.min(b))
+        .register_fn("max", |a: INT, b: INT| a.max(b))
+        .register_fn("clamp", |value: INT, low: INT, high: INT| {
+            value.max(low).min(high)
+        })
+        .register_fn("pitch_class", |pitch: INT| pitch.rem_euclid(7));
+    engine
+}
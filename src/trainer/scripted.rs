@@ -0,0 +1,217 @@
+//! Courses written as scripts in `.drill` asset files, so teachers can add
+//! drills without a build. The file name is the course's name, `leaps.drill`
+//! is the `Leaps` course, and the comments at the top describe it. Scripts are
+//! Rhai, see `script.rs` for what they can call and `assets/drills` for an
+//! example.
+//!
+//! A script defines `next(history)`, returning the staff position of the next
+//! note after those of the round so far, and optionally
+//! `check(pitch, key, placed)` to judge answers: `key` is the pitch class
+//! answered, `placed` where the note was written or `()`.
+
+use std::{ops::RangeInclusive, sync::Arc};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rhai::{Dynamic, INT};
+
+use super::{
+    config::{TrainerAssets, TrainerConfig},
+    register_course,
+    script::Script,
+    Answer, CourseGenerator, TrainCourse,
+};
+
+/// Both staves, as the `All` course.
+const PITCHES: RangeInclusive<i32> = -12..=12;
+
+#[derive(Asset, TypePath, Clone, Debug)]
+pub(super) struct CourseScript {
+    name: String,
+    description: String,
+    script: Arc<Script>,
+}
+
+impl CourseScript {
+    /// The course of the script file `stem`, `treble_leaps` for `TrebleLeaps`.
+    pub fn parse(stem: &str, source: &str) -> anyhow::Result<Self> {
+        let name: String = stem
+            .split(['_', '-', ' '])
+            .flat_map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_uppercase())
+                    .into_iter()
+                    .chain(chars)
+            })
+            .collect();
+        anyhow::ensure!(
+            name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric()),
+            "{} can't name a course, use letters, digits and underscores",
+            stem
+        );
+        let script = Script::parse(source)?;
+        anyhow::ensure!(
+            script.arity("next") == Some(1),
+            "the script needs a next(history) function"
+        );
+        anyhow::ensure!(
+            matches!(script.arity("check"), None | Some(3)),
+            "check takes the pitch, the key and where the note was placed"
+        );
+        let description = source
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty())
+            .map_while(|line| line.strip_prefix("//"))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(CourseScript {
+            name,
+            description,
+            script: Arc::new(script),
+        })
+    }
+
+    /// Adds the course, or replaces the one of an earlier version of the file.
+    pub fn register(&self) -> TrainCourse {
+        register_course(ScriptedCourse {
+            name: TrainCourse::intern(&self.name).name(),
+            description: self.description.clone(),
+            script: self.script.clone(),
+        })
+    }
+}
+
+struct ScriptedCourse {
+    name: &'static str,
+    description: String,
+    script: Arc<Script>,
+}
+
+impl CourseGenerator for ScriptedCourse {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn next_pitch(&self, history: &[i32], rng: &mut dyn RngCore) -> i32 {
+        let history = history.iter().map(|&p| Dynamic::from(p as INT)).collect();
+        let pitch = self
+            .script
+            .call("next", vec![Dynamic::from_array(history)], rng)
+            .and_then(|value| {
+                value
+                    .as_int()
+                    .map_err(|kind| anyhow::anyhow!("expected a number, not {}", kind))
+            });
+        match pitch {
+            Ok(pitch) => pitch.clamp(*PITCHES.start() as INT, *PITCHES.end() as INT) as i32,
+            Err(err) => {
+                println!("Course {} failed to draw a note: {}", self.name, err);
+                0
+            }
+        }
+    }
+
+    fn is_right(&self, pitch: i32, answer: &Answer) -> bool {
+        if self.script.arity("check").is_none() {
+            return answer.names(pitch);
+        }
+        let args = vec![
+            Dynamic::from(pitch as INT),
+            Dynamic::from(answer.key as INT),
+            answer
+                .placed
+                .map_or(Dynamic::UNIT, |placed| Dynamic::from(placed as INT)),
+        ];
+        // Judging the same answer twice gives the same verdict
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        match self.script.call("check", args, &mut rng).and_then(|value| {
+            value
+                .as_bool()
+                .map_err(|kind| anyhow::anyhow!("expected true or false, not {}", kind))
+        }) {
+            Ok(right) => right,
+            Err(err) => {
+                println!("Course {} failed to check an answer: {}", self.name, err);
+                answer.names(pitch)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct CourseScriptLoader;
+
+impl AssetLoader for CourseScriptLoader {
+    type Asset = CourseScript;
+    type Settings = ();
+    type Error = anyhow::Error;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> anyhow::Result<CourseScript> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source).await?;
+        let stem = load_context
+            .path()
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        CourseScript::parse(stem, &source)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["drill"]
+    }
+}
+
+/// Course scripts of `TrainerPlugin::course_scripts`, kept loaded so edited
+/// files apply with Bevy's `file_watcher` feature. Other scripts loaded by
+/// the host aren't courses until it registers them.
+#[derive(Resource, Default)]
+pub(super) struct CourseScripts(Vec<Handle<CourseScript>>);
+
+pub(super) fn course_script_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+) {
+    let handles = config
+        .course_scripts
+        .iter()
+        .map(|path| assets.load(path))
+        .collect();
+    commands.insert_resource(CourseScripts(handles));
+}
+
+pub(super) fn course_script_system(
+    mut events: EventReader<AssetEvent<CourseScript>>,
+    scripts: Res<Assets<CourseScript>>,
+    listed: Res<CourseScripts>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Added { id } | AssetEvent::Modified { id } = event {
+            if !listed.0.iter().any(|handle| handle.id() == *id) {
+                continue;
+            }
+            if let Some(script) = scripts.get(*id) {
+                script.register();
+            }
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::{AsRefStr, EnumIter};
+
+use super::{
+    config::{TrainerAssets, TrainerConfig},
+    gamepad::GamepadMapping,
+    layout::ScaledUi,
+    naming::{NameFont, NameLabel, NoteNaming},
+    profiles::Profile,
+    theme::Theme,
+    Screen,
+};
+use crate::storage;
+
+pub(super) const SETTINGS_KEY: &str = "settings";
+const NOTES_PER_ROUND_CHOICES: [usize; 6] = [8, 12, 16, 20, 24, 30];
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize, EnumIter, AsRefStr,
+)]
+pub(super) enum FeedbackStyle {
+    /// Label every note as soon as it is answered
+    #[default]
+    Immediate,
+    /// Keep the labels hidden until the round is finished
+    EndOfRound,
+    /// Label every note, the cursor waits on it until it is answered right
+    Strict,
+}
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize, EnumIter, AsRefStr,
+)]
+pub(super) enum DrillMode {
+    /// Name the note shown on the staff
+    #[default]
+    Read,
+    /// Place the named note on the staff, any octave
+    Write,
+    /// Place the named note in the octave asked for
+    #[strum(serialize = "Write with octave")]
+    WriteWithOctave,
+    /// Point anywhere on the staff to see and hear the note, nothing is judged
+    Explore,
+}
+
+/// User preferences, loaded once at startup and saved on every change.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Settings {
+    pub notes_per_round: usize,
+    pub mode: DrillMode,
+    pub feedback: FeedbackStyle,
+    /// Flash the note head in the answer's color, except with end of round
+    /// feedback
+    pub flash: bool,
+    pub sound: bool,
+    pub naming: NoteNaming,
+    pub theme: Theme,
+    pub fullscreen: bool,
+    pub gamepad: GamepadMapping,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            notes_per_round: 16,
+            mode: default(),
+            feedback: default(),
+            flash: true,
+            sound: true,
+            naming: default(),
+            theme: default(),
+            fullscreen: true,
+            gamepad: default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(profile: &Profile) -> Self {
+        let Some(contents) = storage::read(&profile.key(SETTINGS_KEY)) else {
+            return default();
+        };
+        match ron::from_str(&contents) {
+            Ok(settings) => settings,
+            Err(err) => {
+                println!("Invalid settings, using defaults: {}", err);
+                default()
+            }
+        }
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&profile.key(SETTINGS_KEY), &contents));
+        if let Err(err) = result {
+            println!("Failed to save settings: {}", err);
+        }
+    }
+}
+
+/// The variant after `value`, wrapping around at the end.
+pub(super) fn cycle<T: IntoEnumIterator + PartialEq>(value: T) -> T {
+    T::iter()
+        .cycle()
+        .skip_while(|v| *v != value)
+        .nth(1)
+        .unwrap()
+}
+
+#[derive(Component)]
+pub(super) struct SettingsScreen;
+
+#[derive(Component, Clone, Copy)]
+pub(super) enum SettingsButton {
+    NotesPerRound,
+    Mode,
+    Feedback,
+    Flash,
+    Sound,
+    Naming,
+    Theme,
+    Fullscreen,
+    Back,
+}
+
+impl SettingsButton {
+    const ALL: [SettingsButton; 9] = [
+        SettingsButton::NotesPerRound,
+        SettingsButton::Mode,
+        SettingsButton::Feedback,
+        SettingsButton::Flash,
+        SettingsButton::Sound,
+        SettingsButton::Naming,
+        SettingsButton::Theme,
+        SettingsButton::Fullscreen,
+        SettingsButton::Back,
+    ];
+
+    pub fn label(self, settings: &Settings, native_names: bool) -> String {
+        fn on_off(value: bool) -> &'static str {
+            if value {
+                "On"
+            } else {
+                "Off"
+            }
+        }
+        match self {
+            SettingsButton::NotesPerRound => {
+                format!("Notes per round: {}", settings.notes_per_round)
+            }
+            SettingsButton::Mode => format!("Mode: {}", settings.mode.as_ref()),
+            SettingsButton::Feedback => format!("Feedback: {}", settings.feedback.as_ref()),
+            SettingsButton::Flash => format!("Flash: {}", on_off(settings.flash)),
+            SettingsButton::Sound => format!("Sound: {}", on_off(settings.sound)),
+            SettingsButton::Naming => format!("Naming: {}", settings.naming.label(native_names)),
+            SettingsButton::Theme => format!("Theme: {}", settings.theme.as_ref()),
+            SettingsButton::Fullscreen => format!("Fullscreen: {}", on_off(settings.fullscreen)),
+            SettingsButton::Back => "Back".to_string(),
+        }
+    }
+
+    fn apply(self, settings: &mut Settings) {
+        match self {
+            SettingsButton::NotesPerRound => {
+                let next = NOTES_PER_ROUND_CHOICES
+                    .iter()
+                    .find(|&&n| n > settings.notes_per_round);
+                settings.notes_per_round = *next.unwrap_or(&NOTES_PER_ROUND_CHOICES[0]);
+            }
+            SettingsButton::Mode => settings.mode = cycle(settings.mode),
+            SettingsButton::Feedback => settings.feedback = cycle(settings.feedback),
+            SettingsButton::Flash => settings.flash = !settings.flash,
+            SettingsButton::Sound => settings.sound = !settings.sound,
+            SettingsButton::Naming => settings.naming = cycle(settings.naming),
+            SettingsButton::Theme => settings.theme = cycle(settings.theme),
+            SettingsButton::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            SettingsButton::Back => {}
+        }
+    }
+}
+
+pub(super) fn settings_screen_setup(
+    mut commands: Commands,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    let palette = settings.theme.palette();
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(8.),
+                    ..config.region_style()
+                },
+                background_color: palette.background.into(),
+                // Keep the trainer buttons underneath from reacting
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            SettingsScreen,
+            ScaledUi,
+        ))
+        .with_children(|parent| {
+            for button in SettingsButton::ALL {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(320.0),
+                                height: Val::Px(50.0),
+                                border: UiRect::all(Val::Px(1.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(palette.button_border),
+                            border_radius: BorderRadius::all(Val::Px(4.)),
+                            image: UiImage::default().with_color(palette.button),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        let mut label = parent.spawn(TextBundle::from_section(
+                            button.label(&settings, name_font.loaded),
+                            TextStyle {
+                                font: assets.font(),
+                                font_size: 22.0,
+                                color: palette.button_text,
+                            },
+                        ));
+                        if matches!(button, SettingsButton::Naming) {
+                            label.insert(NameLabel);
+                        }
+                    });
+            }
+        });
+}
+
+pub(super) fn settings_screen_cleanup(
+    mut commands: Commands,
+    screens: Query<Entity, With<SettingsScreen>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type SettingsButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d SettingsButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+pub(super) fn settings_button_system(
+    mut interaction_query: SettingsButtonQuery,
+    labels: Query<(&SettingsButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>,
+    name_font: Res<NameFont>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let mut changed = name_font.is_changed();
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction == Interaction::Pressed {
+            if let SettingsButton::Back = button {
+                next_screen.set(Screen::Trainer);
+            } else {
+                button.apply(&mut settings);
+                changed = true;
+            }
+        }
+    }
+
+    // Saved by profile_save_system
+    if changed {
+        for (button, children) in &labels {
+            if let Ok(mut text) = text_query.get_mut(children[0]) {
+                text.sections[0].value = button.label(&settings, name_font.loaded);
+            }
+        }
+    }
+}
+
+/// The window mode was picked on the command line, the fullscreen setting
+/// doesn't change it.
+#[derive(Resource)]
+#[cfg_attr(any(target_os = "android", target_arch = "wasm32"), allow(dead_code))]
+pub(crate) struct FixedWindowMode;
+
+/// Applies the settings that live outside the trainer systems, unless the
+/// window belongs to the host.
+pub(super) fn settings_apply(
+    settings: Res<Settings>,
+    mut clear_color: ResMut<ClearColor>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    fixed: Option<Res<FixedWindowMode>>,
+    config: Res<TrainerConfig>,
+) {
+    if !settings.is_changed() || !config.owns_window {
+        return;
+    }
+    clear_color.0 = settings.theme.palette().background;
+    if fixed.is_some() {
+        return;
+    }
+
+    let mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+    // A window drawn into a canvas of the hosting page keeps to it
+    for mut window in windows.iter_mut().filter(|w| w.canvas.is_none()) {
+        if window.mode != mode {
+            window.mode = mode;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use bevy::{audio::PitchBundle, prelude::*};
+
+use super::{naming::pitch_class, settings::Settings, NoteAnswered};
+
+/// Semitones above C of each pitch class in C major.
+const MAJOR_SCALE: [i32; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MIDDLE_C_MIDI: i32 = 60;
+const NOTE_DURATION: Duration = Duration::from_millis(400);
+
+pub(super) fn pitch_frequency(pitch: i32) -> f32 {
+    let octave = pitch.div_euclid(MAJOR_SCALE.len() as i32);
+    let midi = MIDDLE_C_MIDI + octave * 12 + MAJOR_SCALE[pitch_class(pitch)];
+    440. * 2f32.powf((midi - 69) as f32 / 12.)
+}
+
+pub(super) fn play_pitch(commands: &mut Commands, pitches: &mut Assets<Pitch>, pitch: i32) {
+    commands.spawn(PitchBundle {
+        source: pitches.add(Pitch::new(pitch_frequency(pitch), NOTE_DURATION)),
+        settings: PlaybackSettings::DESPAWN,
+    });
+}
+
+/// Plays every answered note, so students hear what they read.
+pub(super) fn sound_answer_system(
+    mut commands: Commands,
+    mut answers: EventReader<NoteAnswered>,
+    settings: Res<Settings>,
+    pitches: Option<ResMut<Assets<Pitch>>>,
+) {
+    let Some(mut pitches) = pitches else {
+        answers.clear();
+        return;
+    };
+    for answer in answers.read() {
+        if settings.sound {
+            play_pitch(&mut commands, &mut pitches, answer.pitch);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    curriculum::{Progress, LESSONS},
+    homework::Homework,
+    profiles::Profile,
+    TrainSequence,
+};
+use crate::storage;
+
+pub(super) const STATS_KEY: &str = "stats";
+
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub(super) struct RoundStats {
+    pub rounds: u32,
+    pub notes: u32,
+    pub correct: u32,
+}
+
+impl RoundStats {
+    pub fn accuracy(&self) -> f32 {
+        if self.notes == 0 {
+            return 0.;
+        }
+        self.correct as f32 / self.notes as f32
+    }
+}
+
+/// Results of the finished rounds, retry rounds are kept apart so they don't
+/// inflate the accuracy of the regular courses. Kept in the student's profile.
+#[derive(Resource, Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct TrainStats {
+    pub course: RoundStats,
+    pub retry: RoundStats,
+    /// Best round accuracy of every course
+    pub best: BTreeMap<String, f32>,
+}
+
+impl TrainStats {
+    pub fn load(profile: &Profile) -> Self {
+        let Some(contents) = storage::read(&profile.key(STATS_KEY)) else {
+            return default();
+        };
+        match ron::from_str(&contents) {
+            Ok(stats) => stats,
+            Err(err) => {
+                println!("Invalid stats, starting over: {}", err);
+                default()
+            }
+        }
+    }
+
+    pub fn save(&self, profile: &Profile) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&profile.key(STATS_KEY), &contents));
+        if let Err(err) = result {
+            println!("Failed to save stats: {}", err);
+        }
+    }
+
+    pub fn record(&mut self, train: &TrainSequence) {
+        let stats = if train.retry {
+            &mut self.retry
+        } else {
+            &mut self.course
+        };
+        let correct = train.notes.iter().filter(|n| n.is_correct()).count() as u32;
+        stats.rounds += 1;
+        stats.notes += train.notes.len() as u32;
+        stats.correct += correct;
+
+        if !train.retry && train.lesson.is_none() {
+            let accuracy = correct as f32 / train.notes.len().max(1) as f32;
+            let best = self
+                .best
+                .entry(train.course.as_ref().to_string())
+                .or_default();
+            *best = best.max(accuracy);
+        }
+    }
+}
+
+/// Everything the student achieved, as printed by the `stats` command.
+pub(crate) fn summary(profile: &Profile) -> String {
+    let stats = TrainStats::load(profile);
+    let progress = Progress::load(profile);
+    let homework = Homework::load(profile);
+    let rounds = |stats: RoundStats| {
+        format!(
+            "{} rounds, {} notes, {:.0}% correct",
+            stats.rounds,
+            stats.notes,
+            stats.accuracy() * 100.
+        )
+    };
+
+    let mut lines = vec![
+        profile.name.clone(),
+        format!("Courses: {}", rounds(stats.course)),
+        format!("Retries: {}", rounds(stats.retry)),
+    ];
+    for (course, best) in &stats.best {
+        lines.push(format!("  {} best round {:.0}%", course, best * 100.));
+    }
+    lines.push("Lessons:".to_string());
+    for (index, lesson) in LESSONS.iter().enumerate() {
+        let state = if progress.is_passed(index) {
+            "passed"
+        } else if progress.is_unlocked(index) {
+            "open"
+        } else {
+            "locked"
+        };
+        let best = progress
+            .best(index)
+            .map(|best| {
+                format!(
+                    ", best {:.0}% at {:.1} s per note",
+                    best.accuracy * 100.,
+                    best.seconds_per_note
+                )
+            })
+            .unwrap_or_default();
+        lines.push(format!("  {}. {} {}{}", index + 1, lesson.name, state, best));
+    }
+    if !homework.work.is_empty() {
+        lines.push("Homework:".to_string());
+    }
+    for work in &homework.work {
+        let done = work
+            .results
+            .iter()
+            .filter(|r| r.as_ref().is_some_and(|r| r.passed))
+            .count();
+        lines.push(format!(
+            "  {}: {} of {} drills done",
+            work.assignment.title,
+            done,
+            work.results.len()
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,976 @@
+//! Headless tests of `TrainerPlugin`: no window and no GPU, inputs are injected
+//! as `GameAction`s and every `update` runs one frame.
+
+use bevy::{
+    a11y::AccessibilityPlugin, ecs::schedule::ExecutorKind, gizmos::GizmoPlugin,
+    input::InputPlugin, state::app::StatesPlugin, window::ExitCondition,
+};
+
+use super::{
+    gamepad::GamepadMapping,
+    recording::{RecordedEvent, RecordedInput, Recording},
+    *,
+};
+use crate::storage;
+
+const SEED: u64 = 7;
+
+fn headless_app() -> App {
+    headless_app_with(TrainerPlugin::default())
+}
+
+fn headless_app_with(plugin: TrainerPlugin) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        StatesPlugin,
+        InputPlugin,
+        WindowPlugin {
+            primary_window: None,
+            exit_condition: ExitCondition::DontExit,
+            close_when_requested: false,
+        },
+        HierarchyPlugin,
+        TransformPlugin,
+        AccessibilityPlugin,
+    ))
+    // Provided by the render and UI plugins in the real app
+    .init_asset::<Shader>()
+    .add_plugins(GizmoPlugin)
+    .init_asset::<Image>()
+    .init_asset::<Font>()
+    .init_asset::<Mesh>()
+    .init_asset::<ColorMaterial>()
+    .init_resource::<ClearColor>()
+    .add_plugins(plugin);
+
+    // Storage goes to the test's own directory, which only the test's thread
+    // knows of
+    app.insert_non_send_resource(storage::TestDataDir::new());
+    for (_, schedule) in app.world_mut().resource_mut::<Schedules>().iter_mut() {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    }
+
+    // Neither the user's settings nor their session recording belong in a test
+    app.world_mut().remove_resource::<Recorder>();
+    app.insert_resource(profiles::Profiles::default().create())
+        .insert_resource(Settings::default())
+        .insert_resource(curriculum::Progress::default())
+        .insert_resource(TrainSequence::new(SEED));
+    app.update();
+    app
+}
+
+fn press(app: &mut App, button: GameButton) {
+    app.world_mut().send_event(GameAction {
+        button,
+        source: InputSource::Pointer,
+    });
+    app.update();
+}
+
+fn train(app: &App) -> &TrainSequence {
+    app.world().resource::<TrainSequence>()
+}
+
+/// The key of the note under the cursor, or a wrong one.
+fn answer(app: &mut App, correct: bool) {
+    let train = train(app);
+    let key = pitch_class(train.notes[train.next_key].pitch);
+    let key = if correct { key } else { (key + 1) % 7 };
+    press(app, GameButton::Answer(key));
+}
+
+fn label(app: &mut App, index: usize) -> Option<(String, String)> {
+    let mut labels = app
+        .world_mut()
+        .query::<(&Text, &NoteSpriteInfo, &Visibility)>();
+    labels
+        .iter(app.world())
+        .find(|(_, info, _)| info.index == index)
+        .filter(|(_, _, visibility)| **visibility == Visibility::Visible)
+        .map(|(text, _, _)| {
+            (
+                text.sections[0].value.clone(),
+                text.sections[1].value.clone(),
+            )
+        })
+}
+
+#[test]
+fn starts_with_the_demo() {
+    let mut app = headless_app();
+    assert_eq!(train(&app).notes.len(), 25);
+    assert_eq!(train(&app).next_key, 0);
+
+    let mut sprites = app
+        .world_mut()
+        .query_filtered::<&Visibility, (With<NoteSpriteInfo>, With<Sprite>)>();
+    let visible = sprites
+        .iter(app.world())
+        .filter(|v| **v == Visibility::Visible)
+        .count();
+    assert_eq!(visible, 25);
+}
+
+#[test]
+fn course_button_switches_course() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_LINES));
+
+    let train = train(&app);
+    assert!(matches!(train.course, TrainCourse::TREBLE_LINES));
+    assert_eq!(train.notes.len(), Settings::default().notes_per_round);
+    assert_eq!(train.next_key, 0);
+    assert!(train
+        .notes
+        .iter()
+        .all(|n| (0..=12).contains(&n.pitch) && n.pitch % 2 == 0));
+}
+
+#[test]
+fn answers_are_labelled() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_ALL));
+    assert_eq!(label(&mut app, 0), None);
+
+    answer(&mut app, true);
+    answer(&mut app, false);
+    let names = Settings::default().naming;
+    let train = train(&app);
+    let (first, second) = (train.notes[0].pitch, train.notes[1].pitch);
+    assert_eq!(train.next_key, 2);
+    assert_eq!(
+        label(&mut app, 0),
+        Some((names.name(first, false).to_string(), String::new()))
+    );
+    assert_eq!(
+        label(&mut app, 1),
+        Some((
+            names.name(second, false).to_string(),
+            WRONG_MARK.to_string()
+        ))
+    );
+    assert_eq!(label(&mut app, 2), None);
+}
+
+#[test]
+fn end_of_round_feedback_hides_labels() {
+    let mut app = headless_app();
+    app.world_mut().resource_mut::<Settings>().feedback = FeedbackStyle::EndOfRound;
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_ALL));
+
+    answer(&mut app, true);
+    assert_eq!(label(&mut app, 0), None);
+    while train(&app).next_key < train(&app).notes.len() {
+        answer(&mut app, true);
+    }
+    assert!(label(&mut app, 0).is_some());
+}
+
+#[test]
+fn finished_round_is_recorded_and_followed_by_the_next() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::BASS_ALL));
+    let num_notes = train(&app).notes.len();
+    let seed = train(&app).seed;
+    for _ in 0..num_notes {
+        answer(&mut app, true);
+    }
+
+    let stats = app.world().resource::<TrainStats>();
+    assert_eq!(stats.course.rounds, 1);
+    assert_eq!(stats.course.correct, num_notes as u32);
+    assert_eq!(train(&app).next_key, num_notes);
+
+    // Any key after the last note starts a new round of the same course
+    press(&mut app, GameButton::Answer(0));
+    let train = train(&app);
+    assert!(matches!(train.course, TrainCourse::BASS_ALL));
+    assert_eq!(train.next_key, 0);
+    assert_ne!(train.seed, seed);
+}
+
+#[test]
+fn retry_drills_the_mistakes() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_ALL));
+    let mistakes: Vec<i32> = train(&app).notes[..3].iter().map(|n| n.pitch).collect();
+    for _ in 0..3 {
+        answer(&mut app, false);
+    }
+
+    press(&mut app, GameButton::Retry);
+    let train = train(&app);
+    assert!(train.retry);
+    assert_eq!(train.notes.len(), RETRY_MIN_NOTES);
+    for pitch in mistakes {
+        assert!(train.notes.iter().any(|n| n.pitch == pitch));
+    }
+
+    // A mistake at the edge of the ledger lines is padded from one side
+    let mut edge = TrainSequence::new(SEED);
+    edge.notes = vec![TrainNote::new(0, 16)];
+    edge.next_key = 1;
+    assert!(edge.gen_retry());
+    assert_eq!(edge.notes.len(), RETRY_MIN_NOTES);
+    assert!(edge.notes.iter().all(|n| (9..=16).contains(&n.pitch)));
+}
+
+#[test]
+fn same_seed_replays_the_same_session() {
+    let play = || {
+        let mut app = headless_app();
+        press(&mut app, GameButton::Course(TrainCourse::ALL));
+        for _ in 0..4 {
+            answer(&mut app, false);
+        }
+        press(&mut app, GameButton::Retry);
+        let pitches: Vec<i32> = train(&app).notes.iter().map(|n| n.pitch).collect();
+        pitches
+    };
+    assert_eq!(play(), play());
+}
+
+#[test]
+fn hot_seat_hands_over_to_the_second_player() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_ALL));
+    press(&mut app, GameButton::Versus(VersusMode::HotSeat));
+    let notes: Vec<i32> = train(&app).notes.iter().map(|n| n.pitch).collect();
+    for _ in 0..notes.len() {
+        answer(&mut app, true);
+    }
+
+    let versus = app.world().resource::<Versus>();
+    assert_eq!(versus.turn, 1);
+    let train = train(&app);
+    assert_eq!(train.next_key, 0);
+    assert!(train.notes.iter().map(|n| n.pitch).eq(notes));
+}
+
+#[test]
+fn replay_drives_the_answers() {
+    let button = |time, button| RecordedEvent {
+        time,
+        input: RecordedInput::Button {
+            button,
+            source: InputSource::Pointer,
+        },
+    };
+    let recording = Recording {
+        seed: SEED,
+        events: vec![
+            button(0., GameButton::Course(TrainCourse::BASS_LINES)),
+            button(0., GameButton::Answer(0)),
+            button(0., GameButton::Answer(1)),
+        ],
+    };
+    let mut app = headless_app();
+    app.insert_resource(Replay::new(recording));
+    app.update();
+
+    let mut live = headless_app();
+    press(&mut live, GameButton::Course(TrainCourse::BASS_LINES));
+    press(&mut live, GameButton::Answer(0));
+    press(&mut live, GameButton::Answer(1));
+
+    let (replayed, live) = (train(&app), train(&live));
+    assert_eq!(replayed.next_key, 2);
+    assert!(replayed
+        .notes
+        .iter()
+        .zip(&live.notes)
+        .all(|(a, b)| a.pitch == b.pitch && a.pressed_key == b.pressed_key));
+}
+
+#[test]
+fn gamepad_answers_and_switches_courses() {
+    use bevy::input::gamepad::{
+        GamepadButtonChangedEvent, GamepadConnection, GamepadConnectionEvent, GamepadEvent,
+        GamepadInfo,
+    };
+
+    // The triggers would also reach the courses other tests register
+    let mut app = headless_app_with(TrainerPlugin {
+        courses: TrainCourse::BUILTIN.to_vec(),
+        ..default()
+    });
+    let gamepad = Gamepad::new(0);
+    app.world_mut()
+        .send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            gamepad,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Test pad".to_string(),
+            }),
+        )));
+    app.update();
+    let tap = |app: &mut App, button: GamepadButtonType| {
+        for value in [1., 0.] {
+            app.world_mut()
+                .send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(
+                    gamepad, button, value,
+                )));
+            app.update();
+        }
+    };
+
+    let mapping = GamepadMapping::default();
+    tap(&mut app, mapping.next_course);
+    assert!(matches!(train(&app).course, TrainCourse::TREBLE_LINES));
+
+    let key = pitch_class(train(&app).notes[0].pitch);
+    tap(&mut app, mapping.answers[key]);
+    assert_eq!(train(&app).next_key, 1);
+    assert!(train(&app).notes[0].is_correct());
+}
+
+#[test]
+fn write_mode_judges_the_written_notes() {
+    let mut app = headless_app();
+    app.world_mut().resource_mut::<Settings>().mode = DrillMode::WriteWithOctave;
+    app.update();
+    assert_eq!(train(&app).mode, DrillMode::WriteWithOctave);
+    assert_eq!(label(&mut app, 0), None);
+
+    let pitch = train(&app).notes[0].pitch;
+    press(&mut app, GameButton::Place(pitch));
+    // An octave off is wrong when the octave is asked for
+    let second = train(&app).notes[1].pitch;
+    press(&mut app, GameButton::Place(second + 7));
+    // Letter answers only count when reading
+    press(&mut app, GameButton::Answer(0));
+
+    let train = train(&app);
+    assert_eq!(train.next_key, 2);
+    assert!(train.notes[0].is_correct());
+    assert!(!train.notes[1].is_correct());
+    assert_eq!(train.notes[1].placed, Some(second + 7));
+    let names = Settings::default().naming;
+    assert_eq!(
+        label(&mut app, 0),
+        Some((names.name_with_octave(pitch, false), String::new()))
+    );
+}
+
+#[test]
+fn pointer_snaps_to_the_nearest_line_or_space() {
+    let app = headless_app();
+    let layout = app.world().resource::<StaffLayout>();
+    let step = layout.space_y * 0.5;
+    for pitch in -16..=16 {
+        let y = layout.pitch_to_y(pitch);
+        assert_eq!(layout.y_to_pitch(y), pitch);
+        assert_eq!(layout.y_to_pitch(y + step * 0.4), pitch);
+        assert_eq!(layout.y_to_pitch(y - step * 0.4), pitch);
+    }
+}
+
+#[test]
+fn explore_mode_judges_nothing() {
+    let mut app = headless_app();
+    app.world_mut().resource_mut::<Settings>().mode = DrillMode::Explore;
+    app.update();
+    press(&mut app, GameButton::Answer(0));
+    press(&mut app, GameButton::Place(0));
+
+    assert_eq!(train(&app).next_key, 0);
+    let mut sprites = app
+        .world_mut()
+        .query_filtered::<&Visibility, (With<NoteSpriteInfo>, With<Sprite>)>();
+    assert!(sprites.iter(app.world()).all(|v| *v == Visibility::Hidden));
+}
+
+#[test]
+fn wrong_answers_show_the_named_note() {
+    assert_eq!(naming::nearest_pitch(0, 2), 2);
+    assert_eq!(naming::nearest_pitch(0, 6), -1);
+    assert_eq!(naming::nearest_pitch(-3, 1), -6);
+
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_ALL));
+    answer(&mut app, true);
+    answer(&mut app, false);
+
+    let pitch = train(&app).notes[1].pitch;
+    let y = app.world().resource::<StaffLayout>().pitch_to_y(pitch + 1);
+    let mut ghosts = app.world_mut().query::<(
+        &mistakes::MistakeGhost,
+        &Transform,
+        &Visibility,
+        Option<&Text>,
+    )>();
+    let shown: Vec<_> = ghosts
+        .iter(app.world())
+        .filter(|(_, _, visibility, _)| **visibility == Visibility::Visible)
+        .collect();
+    assert_eq!(shown.len(), 2);
+    for (_, transform, _, text) in shown {
+        match text {
+            Some(text) => assert_eq!(text.sections[0].value, "1"),
+            None => assert_eq!(transform.translation.y, y),
+        }
+    }
+}
+
+#[test]
+fn strict_feedback_waits_for_the_right_answer() {
+    let mut app = headless_app();
+    app.world_mut().resource_mut::<Settings>().feedback = FeedbackStyle::Strict;
+    press(&mut app, GameButton::Course(TrainCourse::BASS_ALL));
+
+    answer(&mut app, false);
+    answer(&mut app, false);
+    assert_eq!(train(&app).next_key, 0);
+    answer(&mut app, true);
+    answer(&mut app, true);
+
+    let train = train(&app);
+    assert_eq!(train.next_key, 2);
+    assert_eq!(train.notes[0].attempts, 3);
+    assert!(train.notes[0].is_right() && !train.notes[0].is_correct());
+    assert_eq!(train.notes[1].attempts, 1);
+    assert!(train.notes[1].is_correct());
+
+    let mut flashes = app.world_mut().query::<&flash::Flash>();
+    assert!(flashes.iter(app.world()).count() > 0);
+
+    // Matches play by the same rules
+    press(&mut app, GameButton::Versus(VersusMode::HotSeat));
+    app.update();
+    assert!(app.world().resource::<TrainSequence>().strict);
+}
+
+#[test]
+fn passing_a_lesson_unlocks_the_next() {
+    use curriculum::{LessonScore, Progress};
+
+    let mut progress = Progress::default();
+    assert!(progress.is_unlocked(0) && !progress.is_unlocked(1));
+    let slow = LessonScore {
+        accuracy: 1.,
+        seconds_per_note: 10.,
+    };
+    assert!(!progress.record(0, slow));
+    assert!(!progress.is_unlocked(1));
+    let fast = LessonScore {
+        accuracy: 0.95,
+        seconds_per_note: 1.,
+    };
+    assert!(progress.record(0, fast));
+    assert!(progress.is_unlocked(1) && !progress.is_unlocked(2));
+    assert_eq!(progress.best(0), Some(slow));
+}
+
+#[test]
+fn lesson_rounds_follow_each_other() {
+    let mut app = headless_app();
+    press(&mut app, GameButton::Lesson(1));
+    let num_notes = train(&app).notes.len();
+    let lines = [2, 4, 6, 8, 10];
+    assert!(train(&app).notes.iter().all(|n| lines.contains(&n.pitch)));
+    for _ in 0..num_notes {
+        answer(&mut app, true);
+    }
+    assert!(app
+        .world()
+        .resource::<curriculum::Progress>()
+        .best(1)
+        .is_some());
+
+    press(&mut app, GameButton::Answer(0));
+    let train = train(&app);
+    assert_eq!(train.lesson, Some(1));
+    assert_eq!(train.next_key, 0);
+    assert!(train.notes.iter().all(|n| lines.contains(&n.pitch)));
+}
+
+#[test]
+fn profiles_save_apart() {
+    use profiles::Profiles;
+
+    let mut profiles = Profiles::default();
+    let first = profiles.create();
+    let second = profiles.create();
+    assert_eq!(second.name, "Student 2");
+    assert_ne!(first.key("stats"), second.key("stats"));
+
+    let mut app = headless_app();
+    app.insert_resource(second.clone());
+    app.world_mut().resource_mut::<Settings>().sound = false;
+    press(&mut app, GameButton::Course(TrainCourse::TREBLE_LINES));
+    for _ in 0..train(&app).notes.len() {
+        answer(&mut app, true);
+    }
+
+    assert!(!Settings::load(&second).sound);
+    let stats = TrainStats::load(&second);
+    assert_eq!(stats.course.rounds, 1);
+    assert_eq!(stats.best.get("TrebleLines"), Some(&1.));
+}
+
+#[test]
+fn homework_rounds_fill_the_report() {
+    use homework::{Assignment, Homework, Report};
+
+    assert!(Assignment::parse(r#"Assignment(title: "Empty", drills: [])"#).is_err());
+    let assignment = Assignment::parse(
+        r#"Assignment(
+            title: "Week 1",
+            due: Some("2000-01-01"),
+            drills: [(course: "BassLines", seed: 3, notes: 4, required_accuracy: 0.9)],
+        )"#,
+    )
+    .unwrap();
+    let mut app = headless_app();
+    app.world_mut()
+        .resource_mut::<Homework>()
+        .import(assignment);
+    press(
+        &mut app,
+        GameButton::Seeded {
+            course: TrainCourse::BASS_LINES,
+            notes: 4,
+            seed: 3,
+        },
+    );
+    assert_eq!(train(&app).seed, 3);
+    answer(&mut app, false);
+    for _ in 0..3 {
+        answer(&mut app, true);
+    }
+
+    let homework = app.world().resource::<Homework>();
+    let result = homework.work[0].results[0].clone().unwrap();
+    assert_eq!(result.accuracy, 0.75);
+    assert!(!result.passed);
+    assert_eq!(result.notes.len(), 4);
+    assert!(!result.notes[0].correct && result.notes[1].correct);
+    let report = Report::new(&homework.work[0], "Student 1");
+    assert_eq!(report.student, "Student 1");
+    assert_eq!(report.drills[0].1, Some(result));
+}
+
+/// Stand-in LRS on a local port, answering the nth post with `status(n)`.
+/// Passes the posted statements on with their index.
+fn stand_in_lrs(
+    status: impl Fn(usize) -> &'static str + Send + 'static,
+) -> (u16, std::sync::mpsc::Receiver<(usize, serde_json::Value)>) {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (posts, received) = mpsc::channel();
+    std::thread::spawn(move || {
+        for (index, stream) in listener.incoming().enumerate() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut content_length = 0;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                line.clear();
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status(index));
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            if posts.send((index, body)).is_err() {
+                break;
+            }
+        }
+    });
+    (port, received)
+}
+
+#[test]
+fn statements_wait_in_the_queue_for_the_lrs() {
+    use std::time::{Duration, Instant};
+    use xapi::{Lrs, LrsConfig};
+
+    // Down for the first post
+    let (port, received) = stand_in_lrs(|index| match index {
+        0 => "503 Unavailable",
+        _ => "200 OK",
+    });
+    let mut app = headless_app();
+    app.insert_resource(Lrs::new(LrsConfig {
+        endpoint: format!("http://127.0.0.1:{}/xapi", port),
+        accounts: [("Student".to_string(), "s.one".to_string())].into(),
+        retry_seconds: 0.,
+        ..default()
+    }));
+    press(&mut app, GameButton::Course(TrainCourse::BASS_LINES));
+    for _ in 0..train(&app).notes.len() {
+        answer(&mut app, true);
+    }
+
+    let mut stored = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while stored.len() < 3 && Instant::now() < deadline {
+        app.update();
+        while let Ok((index, body)) = received.try_recv() {
+            assert_eq!(body[0]["actor"]["name"], "Student");
+            assert_eq!(body[0]["actor"]["account"]["name"], "s.one");
+            if index > 0 {
+                stored.extend(body.as_array().unwrap().clone());
+            }
+        }
+    }
+    let verbs: Vec<_> = stored.iter().map(|s| s["verb"]["id"].clone()).collect();
+    assert_eq!(
+        verbs,
+        [
+            "http://adlnet.gov/expapi/verbs/attempted",
+            "http://adlnet.gov/expapi/verbs/completed",
+            "http://adlnet.gov/expapi/verbs/scored",
+        ]
+    );
+    assert_eq!(stored[2]["result"]["score"]["scaled"], 1.);
+    app.update();
+    assert!(app.world().resource::<Lrs>().queue.is_empty());
+}
+
+#[test]
+fn statements_stay_queued_when_the_lrs_refuses_the_login() {
+    use std::time::{Duration, Instant};
+    use xapi::{Lrs, LrsConfig};
+
+    let (port, received) = stand_in_lrs(|_| "401 Unauthorized");
+    let mut app = headless_app();
+    app.insert_resource(Lrs::new(LrsConfig {
+        endpoint: format!("http://127.0.0.1:{}/xapi", port),
+        username: "key".to_string(),
+        password: "wrong".to_string(),
+        retry_seconds: 0.,
+        ..default()
+    }));
+    press(&mut app, GameButton::Course(TrainCourse::BASS_LINES));
+    for _ in 0..train(&app).notes.len() {
+        answer(&mut app, true);
+    }
+
+    let mut refused = 0;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while refused < 3 && Instant::now() < deadline {
+        app.update();
+        refused += received.try_iter().count();
+    }
+    assert!(refused >= 3);
+    assert_eq!(app.world().resource::<Lrs>().queue.len(), 3);
+}
+
+#[test]
+fn host_commands_drive_the_trainer() {
+    let mut app = headless_app();
+    let send = |app: &mut App, command| {
+        app.world_mut().send_event(command);
+        app.update();
+    };
+    send(
+        &mut app,
+        HostCommand::Start {
+            course: "basslines".to_string(),
+            seed: Some("week3".to_string()),
+        },
+    );
+    assert_eq!(train(&app).course, TrainCourse::BASS_LINES);
+    assert_eq!(train(&app).seed, embed::parse_seed("week3"));
+    assert_eq!(embed::parse_seed("42"), 42);
+
+    send(
+        &mut app,
+        HostCommand::Settings(r#"{"notes_per_round": 8}"#.into()),
+    );
+    send(&mut app, HostCommand::Settings(r#"{"volume": 3}"#.into()));
+    assert_eq!(app.world().resource::<Settings>().notes_per_round, 8);
+
+    send(&mut app, HostCommand::Pause);
+    answer(&mut app, true);
+    assert_eq!(train(&app).next_key, 0);
+    send(&mut app, HostCommand::Resume);
+    for _ in 0..train(&app).notes.len() {
+        answer(&mut app, true);
+    }
+    let rounds = app.world().resource::<Events<RoundCompleted>>();
+    let round = rounds.iter_current_update_events().last().unwrap();
+    assert_eq!(round.course, "BassLines");
+    assert_eq!(round.accuracy, 1.);
+    assert!(round.notes.iter().all(|n| n.answer.is_some()));
+
+    send(
+        &mut app,
+        HostCommand::ImportHomework(
+            r#"Assignment(title: "Week 2", drills: [(course: "All", seed: 1, notes: 8, required_accuracy: 0.5)])"#.into(),
+        ),
+    );
+    let homework = app.world().resource::<homework::Homework>();
+    assert_eq!(homework.work[0].assignment.title, "Week 2");
+}
+
+#[test]
+fn launch_options_start_the_linked_drill() {
+    let parameters = [("course", "BassLines"), ("notes", "24"), ("seed", "abc")];
+    let launch = LaunchOptions::parse(parameters.into_iter().chain([("utm_source", "chat")]));
+    assert!(LaunchOptions::parse([("notes", "0")]).is_err());
+    assert!(LaunchOptions::parse([("course", "Viola")]).is_err());
+
+    let mut app = headless_app();
+    app.insert_resource(launch.unwrap());
+    app.update();
+    assert!(!app.world().contains_resource::<LaunchOptions>());
+    let train = train(&app);
+    assert_eq!(train.course, TrainCourse::BASS_LINES);
+    assert_eq!(train.notes.len(), 24);
+    assert_eq!(train.seed, embed::parse_seed("abc"));
+}
+
+#[test]
+fn stats_summary_reports_the_profile() {
+    use profiles::Profiles;
+
+    let _dir = storage::TestDataDir::new();
+    let mut profiles = Profiles::default();
+    profiles.create();
+    let profile = profiles.create();
+    assert_eq!(profiles.find(Some("Student 2")).unwrap(), &profile);
+    assert!(profiles.find(Some("Nobody")).is_err());
+
+    let mut train = TrainSequence::new(SEED);
+    train.gen_course_seeded(TrainCourse::TREBLE_LINES, 4, SEED);
+    for note in &mut train.notes {
+        note.pressed_key = Some(pitch_class(note.pitch));
+        note.right = true;
+        note.attempts = 1;
+    }
+    let mut stats = TrainStats::default();
+    stats.record(&train);
+    stats.save(&profile);
+
+    let summary = stats::summary(&profile);
+    assert!(summary.contains("Courses: 1 rounds, 4 notes, 100% correct"));
+    assert!(summary.contains("TrebleLines best round 100%"));
+    assert!(summary.contains("1. Treble landmarks open"));
+}
+
+#[test]
+fn plugin_options_fit_the_trainer_into_a_host_app() {
+    use bevy::render::view::RenderLayers;
+
+    let mut app = headless_app_with(TrainerPlugin {
+        courses: vec![TrainCourse::BASS_LINES, TrainCourse::BASS_ALL],
+        region: Rect::new(0.5, 0., 1., 1.),
+        render_layer: 3,
+        spawn_camera: false,
+        asset_root: "staff_trainer/".to_string(),
+        course_scripts: Vec::new(),
+    });
+    let mut buttons = app.world_mut().query::<&GameButton>();
+    let courses: Vec<_> = buttons
+        .iter(app.world())
+        .filter_map(|button| match button {
+            GameButton::Course(course) => Some(*course),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(courses, [TrainCourse::BASS_LINES, TrainCourse::BASS_ALL]);
+    // The host's window keeps its look
+    let clear_color = app.world().resource::<ClearColor>().0;
+    assert_eq!(clear_color, ClearColor::default().0);
+
+    let mut cameras = app.world_mut().query::<&TrainerCamera>();
+    assert_eq!(cameras.iter(app.world()).count(), 0);
+    let mut clefs = app
+        .world_mut()
+        .query::<(&ClefSprite, &RenderLayers, &Handle<Image>)>();
+    for (_, layers, texture) in clefs.iter(app.world()) {
+        assert_eq!(*layers, RenderLayers::layer(3));
+        let path = texture.path().unwrap().to_string();
+        assert!(path.starts_with("staff_trainer/mtb_images/"), "{}", path);
+    }
+
+    // The right half of a 1200 x 540 window
+    let config = app.world().resource::<TrainerConfig>();
+    let (size, center) = config.world_region(Vec2::new(1200., 540.));
+    let layout = StaffLayout::new(size, center);
+    assert_eq!(layout.scale, 0.5);
+    assert!(layout.staff_x > 0. && layout.staff_x + layout.staff_width < 600.);
+
+    // Only the trainer's own nodes shrink with it
+    app.insert_resource(layout);
+    let host = app
+        .world_mut()
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(100.),
+                ..default()
+            },
+            ..default()
+        })
+        .id();
+    app.update();
+    let mut keys = app
+        .world_mut()
+        .query_filtered::<&Style, With<layout::AnswerKey>>();
+    let widths: Vec<_> = keys.iter(app.world()).map(|style| style.width).collect();
+    assert!(!widths.is_empty() && widths.iter().all(|width| *width == Val::Px(50.)));
+    assert_eq!(app.world().get::<Style>(host).unwrap().width, Val::Px(100.));
+}
+
+#[test]
+fn registered_courses_draw_and_judge_their_notes() {
+    /// Climbs the octaves of B, the leading tone, resolving to C counts too
+    struct LeadingTone;
+
+    impl CourseGenerator for LeadingTone {
+        fn name(&self) -> &'static str {
+            "LeadingTone"
+        }
+
+        fn next_pitch(&self, history: &[i32], _rng: &mut dyn rand::RngCore) -> i32 {
+            history.last().map_or(-1, |last| last + 7)
+        }
+
+        fn is_right(&self, _pitch: i32, answer: &Answer) -> bool {
+            matches!(answer.key, 0 | 6)
+        }
+    }
+
+    let course = register_course(LeadingTone);
+    assert_eq!(TrainCourse::named("LeadingTone"), Some(course));
+    assert!(TrainCourse::all().contains(&course));
+    let saved = ron::to_string(&GameButton::Course(course)).unwrap();
+    assert_eq!(saved, r#"Course("LeadingTone")"#);
+    // Names loaded before their course registers are kept once
+    let later = TrainCourse::intern("Later").name();
+    assert!(std::ptr::eq(later, TrainCourse::intern("Later").name()));
+
+    /// Claims the name of the fallback course
+    struct NotAll;
+
+    impl CourseGenerator for NotAll {
+        fn name(&self) -> &'static str {
+            "All"
+        }
+
+        fn next_pitch(&self, _history: &[i32], _rng: &mut dyn rand::RngCore) -> i32 {
+            99
+        }
+    }
+
+    register_course(NotAll);
+    assert_eq!(TrainCourse::ALL.generator().description(), "both staves");
+
+    let mut app = headless_app();
+    press(&mut app, GameButton::Course(course));
+    let pitches: Vec<_> = train(&app).notes.iter().map(|n| n.pitch).collect();
+    assert_eq!(pitches[..3], [-1, 6, 13]);
+    // Any B or C is right, D isn't
+    press(&mut app, GameButton::Answer(6));
+    press(&mut app, GameButton::Answer(1));
+    let train = train(&app);
+    assert!(train.notes[0].is_correct());
+    assert!(!train.notes[1].is_correct());
+}
+
+#[test]
+fn course_scripts_join_the_course_bar_and_judge_their_notes() {
+    let source = "
+        // Climbs the treble staff by thirds
+        // from middle C, any C is right
+
+        fn next(history) {
+            let n = len(history);
+            if n == 0 {
+                return 0;
+            }
+            history[n - 1] + 2
+        }
+
+        fn check(pitch, key, placed) {
+            key == 0 || key == pitch_class(pitch)
+        }
+    ";
+    assert!(scripted::CourseScript::parse("thirds!", source).is_err());
+    assert!(scripted::CourseScript::parse("thirds", "fn check(a, b, c) { true }").is_err());
+    let stuck = script::Script::parse("fn next(history) { while true {} }").unwrap();
+    let mut rng = ChaCha8Rng::seed_from_u64(SEED);
+    assert!(stuck
+        .call("next", vec![rhai::Dynamic::UNIT], &mut rng)
+        .is_err());
+    // Nesting is limited before it can overflow the stack
+    for (open, close) in [("(", ")"), ("[", "]"), ("-", ""), ("!", "")] {
+        let nested = format!(
+            "fn next(h) {{ {}1{} }}",
+            open.repeat(10_000),
+            close.repeat(10_000)
+        );
+        assert!(script::Script::parse(&nested).is_err());
+    }
+    let recursive = script::Script::parse("fn next(h) { next(h) }").unwrap();
+    assert!(recursive
+        .call("next", vec![rhai::Dynamic::UNIT], &mut rng)
+        .is_err());
+
+    let mut app = headless_app();
+    let course = scripted::CourseScript::parse("treble_thirds", source)
+        .unwrap()
+        .register();
+    assert_eq!(course.as_ref(), "TrebleThirds");
+    assert_eq!(
+        course.generator().description(),
+        "Climbs the treble staff by thirds from middle C, any C is right"
+    );
+    app.update();
+    let mut buttons = app.world_mut().query::<&GameButton>();
+    assert!(buttons
+        .iter(app.world())
+        .any(|button| matches!(button, GameButton::Course(c) if *c == course)));
+
+    press(&mut app, GameButton::Course(course));
+    let pitches: Vec<_> = train(&app).notes.iter().map(|n| n.pitch).collect();
+    assert_eq!(pitches[..4], [0, 2, 4, 6]);
+    // E answered with C is right, G answered with D isn't
+    press(&mut app, GameButton::Answer(0));
+    press(&mut app, GameButton::Answer(0));
+    press(&mut app, GameButton::Answer(1));
+    let train = train(&app);
+    assert!(train.notes[1].is_correct());
+    assert!(!train.notes[2].is_correct());
+
+    // The bundled drill alternates the staves
+    let leaps = include_str!("../../assets/drills/leaps.drill");
+    let course = scripted::CourseScript::parse("leaps", leaps)
+        .unwrap()
+        .register();
+    let mut train = TrainSequence::new(SEED);
+    train.gen_course_seeded(course, STAFF_MAX_NOTES, SEED);
+    for (index, note) in train.notes.iter().enumerate() {
+        match index % 2 {
+            0 => assert!((0..=12).contains(&note.pitch)),
+            _ => assert!((-12..=0).contains(&note.pitch)),
+        }
+    }
+}
+
+#[test]
+fn cli_sizes_are_finite_and_positive() {
+    let parse = |size: &str| crate::cli::Cli::parse(["--size".to_string(), size.to_string()]);
+    assert!(parse("1280x720").is_ok());
+    for size in ["0x0", "-800x600", "NaNx600", "1280xinf", "1280"] {
+        let Err(err) = parse(size) else {
+            panic!("{} passed", size);
+        };
+        assert_eq!(
+            err.to_string(),
+            format!("sizes are like 1280x720, not {}", size)
+        );
+    }
+}
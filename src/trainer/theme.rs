@@ -0,0 +1,210 @@
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumIter};
+
+use super::{
+    engraving::EngravingMesh, layout::TrainerNodes, settings::Settings, settings::SettingsScreen,
+};
+
+#[derive(
+    Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize, EnumIter, AsRefStr,
+)]
+pub(super) enum Theme {
+    #[default]
+    Light,
+    Dark,
+    HighContrast,
+    /// Okabe-Ito colors, correct and wrong stay apart for every color vision
+    ColorBlindSafe,
+}
+
+pub(super) struct Palette {
+    pub background: Color,
+    /// Color of the staff, the clefs and the note heads
+    pub ink: Color,
+    pub cursor: Color,
+    pub button: Color,
+    pub button_hovered: Color,
+    pub button_pressed: Color,
+    pub button_border: Color,
+    pub button_border_pressed: Color,
+    pub button_text: Color,
+    pub correct: Color,
+    pub wrong: Color,
+    /// Cursor and keyboard border of each player in a race
+    pub players: [Color; 2],
+}
+
+const LIGHT: Palette = Palette {
+    background: Color::srgb(0.95, 0.95, 0.95),
+    ink: Color::BLACK,
+    cursor: Color::srgb(0., 0., 1.),
+    button: Color::srgb(0.15, 0.15, 0.15),
+    button_hovered: Color::srgb(0.25, 0.25, 0.25),
+    button_pressed: Color::srgb(0.35, 0.75, 0.35),
+    button_border: Color::BLACK,
+    button_border_pressed: Color::WHITE,
+    button_text: Color::srgb(0.9, 0.9, 0.9),
+    correct: Color::srgb(0., 1., 0.),
+    wrong: Color::srgb(1., 0., 0.),
+    players: [Color::srgb(0., 0., 1.), Color::srgb(0.9, 0.45, 0.)],
+};
+
+const DARK: Palette = Palette {
+    background: Color::srgb(0.08, 0.08, 0.1),
+    ink: Color::srgb(0.9, 0.9, 0.9),
+    cursor: Color::srgb(0.4, 0.6, 1.),
+    button: Color::srgb(0.2, 0.2, 0.24),
+    button_hovered: Color::srgb(0.3, 0.3, 0.36),
+    button_pressed: Color::srgb(0.3, 0.6, 0.3),
+    button_border: Color::srgb(0.4, 0.4, 0.45),
+    button_border_pressed: Color::WHITE,
+    button_text: Color::srgb(0.95, 0.95, 0.95),
+    correct: Color::srgb(0.3, 0.9, 0.3),
+    wrong: Color::srgb(1., 0.35, 0.35),
+    players: [Color::srgb(0.4, 0.6, 1.), Color::srgb(1., 0.6, 0.2)],
+};
+
+const HIGH_CONTRAST: Palette = Palette {
+    background: Color::WHITE,
+    ink: Color::BLACK,
+    cursor: Color::srgb(0., 0., 0.8),
+    button: Color::BLACK,
+    button_hovered: Color::srgb(0., 0., 0.5),
+    button_pressed: Color::srgb(1., 0.85, 0.),
+    button_border: Color::WHITE,
+    button_border_pressed: Color::BLACK,
+    button_text: Color::WHITE,
+    correct: Color::srgb(0., 0.45, 0.),
+    wrong: Color::srgb(0.75, 0., 0.),
+    players: [Color::srgb(0., 0., 0.8), Color::srgb(0.75, 0.3, 0.)],
+};
+
+const COLOR_BLIND_SAFE: Palette = Palette {
+    background: Color::srgb(0.95, 0.95, 0.95),
+    ink: Color::BLACK,
+    cursor: Color::srgb(0.8, 0.47, 0.65),
+    button: Color::srgb(0.15, 0.15, 0.15),
+    button_hovered: Color::srgb(0.25, 0.25, 0.25),
+    button_pressed: Color::srgb(0., 0.45, 0.7),
+    button_border: Color::BLACK,
+    button_border_pressed: Color::WHITE,
+    button_text: Color::srgb(0.9, 0.9, 0.9),
+    correct: Color::srgb(0., 0.45, 0.7),
+    wrong: Color::srgb(0.84, 0.37, 0.),
+    players: [Color::srgb(0., 0.45, 0.7), Color::srgb(0.9, 0.6, 0.)],
+};
+
+/// Drawn next to the label of a wrong answer, so correctness doesn't rely on
+/// color alone.
+pub(super) const WRONG_MARK: &str = "×";
+
+impl Theme {
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Light => &LIGHT,
+            Theme::Dark => &DARK,
+            Theme::HighContrast => &HIGH_CONTRAST,
+            Theme::ColorBlindSafe => &COLOR_BLIND_SAFE,
+        }
+    }
+}
+
+impl Palette {
+    pub fn paint_button(
+        &self,
+        interaction: Interaction,
+        image: &mut UiImage,
+        border_color: &mut BorderColor,
+    ) {
+        let (color, border) = match interaction {
+            Interaction::Pressed => (self.button_pressed, self.button_border_pressed),
+            Interaction::Hovered => (self.button_hovered, self.button_border),
+            Interaction::None => (self.button, self.button_border),
+        };
+        image.color = color;
+        border_color.0 = border;
+    }
+}
+
+/// Sprites drawn in the theme's ink color.
+#[derive(Component)]
+pub(super) struct Inked;
+
+/// The engraving images are black glyphs, which can't be tinted. Once loaded
+/// they are turned white, keeping the alpha, so the sprite color decides.
+pub(super) fn theme_prepare_textures(
+    mut events: EventReader<AssetEvent<Image>>,
+    mut images: ResMut<Assets<Image>>,
+    inked: Query<&Handle<Image>, With<Inked>>,
+) {
+    for event in events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        if !inked.iter().any(|handle| handle.id() == *id) {
+            continue;
+        }
+        let Some(image) = images.get_mut(*id) else {
+            continue;
+        };
+        if image.texture_descriptor.format != TextureFormat::Rgba8UnormSrgb {
+            continue;
+        }
+        for pixel in image.data.chunks_exact_mut(4) {
+            pixel[..3].fill(255);
+        }
+    }
+}
+
+type ThemedButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d Children,
+    ),
+    With<Button>,
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn theme_apply(
+    settings: Res<Settings>,
+    mut sprites: Query<&mut Sprite, With<Inked>>,
+    engravings: Query<&Handle<ColorMaterial>, With<EngravingMesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    trainer_nodes: TrainerNodes,
+    mut buttons: ThemedButtonQuery,
+    mut text_query: Query<&mut Text>,
+    mut screens: Query<&mut BackgroundColor, With<SettingsScreen>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let palette = settings.theme.palette();
+    for mut sprite in &mut sprites {
+        sprite.color = palette.ink;
+    }
+    for handle in &engravings {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = palette.ink;
+        }
+    }
+    for entity in trainer_nodes.iter() {
+        let Ok((interaction, mut image, mut border_color, children)) = buttons.get_mut(entity)
+        else {
+            continue;
+        };
+        palette.paint_button(*interaction, &mut image, &mut border_color);
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            for section in text.sections.iter_mut() {
+                section.style.color = palette.button_text;
+            }
+        }
+    }
+    for mut background in &mut screens {
+        background.0 = palette.background;
+    }
+}
@@ -0,0 +1,525 @@
+use std::{cmp::Reverse, time::Duration};
+
+use bevy::{
+    a11y::{
+        accesskit::{NodeBuilder, Role},
+        AccessibilityNode,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::{TrainerAssets, TrainerConfig, TrainerGizmos},
+    layout::{ScaledUi, StaffLayout},
+    naming::{NameFont, NameLabel},
+    settings::{DrillMode, Settings},
+    NoteAnswered, Screen, TrainCourse, TrainSequence,
+};
+
+const NUM_PLAYERS: usize = 2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub(super) enum VersusMode {
+    /// Players take turns on the same notes
+    HotSeat,
+    /// Players answer the same notes at the same time
+    Race,
+}
+
+pub(super) struct VersusPlayer {
+    pub train: TrainSequence,
+    started: Option<Duration>,
+    time: Option<Duration>,
+}
+
+/// A two player match, only present while one is running. Both players get
+/// their own sequence generated from the same seed.
+#[derive(Resource)]
+pub(super) struct Versus {
+    pub mode: VersusMode,
+    pub players: [VersusPlayer; NUM_PLAYERS],
+    /// Player answering in hot-seat mode
+    pub turn: usize,
+}
+
+impl VersusPlayer {
+    fn correct(&self) -> usize {
+        self.train.notes.iter().filter(|n| n.is_correct()).count()
+    }
+
+    fn finished(&self) -> bool {
+        self.time.is_some()
+    }
+
+    fn summary(&self, player: usize) -> String {
+        let num_notes = self.train.notes.len();
+        let correct = self.correct();
+        format!(
+            "Player {}: {} of {} correct ({:.0}%), {:.1} s",
+            player + 1,
+            correct,
+            num_notes,
+            correct as f32 / num_notes.max(1) as f32 * 100.,
+            self.time.unwrap_or_default().as_secs_f32()
+        )
+    }
+}
+
+impl Versus {
+    pub fn new(
+        mode: VersusMode,
+        course: TrainCourse,
+        num_notes: usize,
+        seed: u64,
+        drill: DrillMode,
+    ) -> Self {
+        let players = std::array::from_fn(|_| {
+            let mut train = TrainSequence::new(seed);
+            // Racers answer on the keyboards, there is only one staff to write on,
+            // and exploring is no match
+            train.mode = match (mode, drill) {
+                (VersusMode::HotSeat, DrillMode::Write | DrillMode::WriteWithOctave) => drill,
+                _ => DrillMode::Read,
+            };
+            train.gen_course_seeded(course, num_notes, seed);
+            VersusPlayer {
+                train,
+                started: None,
+                time: None,
+            }
+        });
+        Versus {
+            mode,
+            players,
+            turn: 0,
+        }
+    }
+
+    /// The most correct answers win, the faster player breaks a tie.
+    fn winner(&self) -> Option<usize> {
+        let score = |p: &VersusPlayer| (p.correct(), Reverse(p.time.unwrap_or(Duration::MAX)));
+        let (first, second) = (score(&self.players[0]), score(&self.players[1]));
+        match first.cmp(&second) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn banner(&self) -> String {
+        match self.mode {
+            VersusMode::HotSeat => format!("Player {}'s turn", self.turn + 1),
+            VersusMode::Race => "Race".to_string(),
+        }
+    }
+}
+
+/// Tells who is playing, shown in a corner while a match runs.
+#[derive(Component)]
+pub(super) struct VersusBanner;
+
+/// Root node of the race keyboards and of the winner screen.
+#[derive(Component)]
+pub(super) struct VersusScreen;
+
+#[derive(Component, Clone, Copy)]
+pub(super) enum VersusButton {
+    Answer {
+        player: usize,
+        key: usize,
+    },
+    /// Ends the match and goes back to the trainer
+    Quit,
+}
+
+/// Spawns a button named for assistive technology, for the screens other than
+/// the trainer and the settings.
+pub(super) fn spawn_button(
+    parent: &mut ChildBuilder,
+    button: impl Component,
+    style: Style,
+    text: impl Bundle,
+    name: String,
+    palette: &super::theme::Palette,
+) {
+    let mut node = NodeBuilder::new(Role::Button);
+    node.set_name(name);
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    border: UiRect::all(Val::Px(1.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..style
+                },
+                border_color: BorderColor(palette.button_border),
+                border_radius: BorderRadius::all(Val::Px(4.)),
+                image: UiImage::default().with_color(palette.button),
+                ..default()
+            },
+            button,
+            AccessibilityNode(node),
+        ))
+        .with_children(|parent| {
+            parent.spawn(text);
+        });
+}
+
+pub(super) fn versus_banner_system(
+    mut commands: Commands,
+    versus: Option<Res<Versus>>,
+    mut banners: Query<(Entity, &mut Text), With<VersusBanner>>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let Some(versus) = versus else {
+        for (entity, _) in &banners {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if !versus.is_changed() && !settings.is_changed() {
+        return;
+    }
+    let style = TextStyle {
+        font: assets.font(),
+        font_size: 28.0,
+        color: settings.theme.palette().ink,
+    };
+    if banners.is_empty() {
+        commands.spawn((
+            TextBundle::from_section(versus.banner(), style).with_style(config.corner_style()),
+            VersusBanner,
+            ScaledUi,
+        ));
+    } else {
+        for (_, mut text) in &mut banners {
+            text.sections[0] = TextSection::new(versus.banner(), style.clone());
+        }
+    }
+}
+
+/// Hands the device to the next player once a hot-seat round is finished.
+pub(super) fn versus_turn_system(
+    mut versus: ResMut<Versus>,
+    mut train: ResMut<TrainSequence>,
+    mut next_screen: ResMut<NextState<Screen>>,
+    time: Res<Time>,
+) {
+    if versus.mode != VersusMode::HotSeat || !train.is_changed() {
+        return;
+    }
+    let now = time.elapsed();
+    let turn = versus.turn;
+    let player = &mut versus.players[turn];
+    // The clock starts when the turn is handed over, reading the first note
+    // counts too
+    let started = *player.started.get_or_insert(now);
+    if train.next_key < train.notes.len() {
+        return;
+    }
+
+    player.time = Some(now - started);
+    player.train = train.clone();
+    if turn + 1 < NUM_PLAYERS {
+        versus.turn += 1;
+        versus.players[turn + 1].started = Some(now);
+        *train = versus.players[turn + 1].train.clone();
+    } else {
+        next_screen.set(Screen::Winner);
+    }
+}
+
+/// Shows both cursors on the shared staff, the second player's is drawn larger
+/// so they stay apart on the same note.
+pub(super) fn versus_cursor_system(
+    mut gizmos: Gizmos<TrainerGizmos>,
+    versus: Res<Versus>,
+    layout: Res<StaffLayout>,
+    settings: Res<Settings>,
+) {
+    let palette = settings.theme.palette();
+    for (index, player) in versus.players.iter().enumerate() {
+        let train = &player.train;
+        let next_key = train.next_key;
+        if next_key >= train.notes.len() {
+            continue;
+        }
+        let note_space = layout.note_space(train.notes.len());
+        let x = layout.index_to_x(next_key, note_space);
+        let y = layout.pitch_to_y(train.notes[next_key].pitch);
+        let size = (34. + 8. * index as f32) * layout.scale;
+        gizmos.rect_2d(
+            Vec2::new(x, y),
+            0.,
+            Vec2::splat(size),
+            palette.players[index],
+        );
+    }
+}
+
+pub(super) fn race_screen_setup(
+    mut commands: Commands,
+    mut versus: ResMut<Versus>,
+    time: Res<Time>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+    name_font: Res<NameFont>,
+) {
+    let now = time.elapsed();
+    for player in versus.players.iter_mut() {
+        player.started = Some(now);
+    }
+
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    let text = |value: String, font_size: f32, color: Color| {
+        TextBundle::from_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color,
+            },
+        )
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::SpaceBetween,
+                    flex_direction: FlexDirection::Column,
+                    ..config.region_style()
+                },
+                ..default()
+            },
+            VersusScreen,
+            ScaledUi,
+        ))
+        .with_children(|root| {
+            spawn_button(
+                root,
+                VersusButton::Quit,
+                Style {
+                    width: Val::Px(130.0),
+                    height: Val::Px(65.0),
+                    ..default()
+                },
+                text("Quit".to_string(), 18., palette.button_text),
+                "Quit race".to_string(),
+                palette,
+            );
+
+            // One keyboard per player, side by side
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::SpaceEvenly,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|keyboards| {
+                for player in 0..NUM_PLAYERS {
+                    keyboards
+                        .spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Percent(48.0),
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                border: UiRect::all(Val::Px(3.0)),
+                                padding: UiRect::all(Val::Px(4.0)),
+                                ..default()
+                            },
+                            border_color: BorderColor(palette.players[player]),
+                            border_radius: BorderRadius::all(Val::Px(6.)),
+                            ..default()
+                        })
+                        .with_children(|keyboard| {
+                            keyboard.spawn(text(
+                                format!("Player {}", player + 1),
+                                24.,
+                                palette.players[player],
+                            ));
+                            keyboard
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|keys| {
+                                    for (key, name) in settings
+                                        .naming
+                                        .names(name_font.loaded)
+                                        .into_iter()
+                                        .enumerate()
+                                    {
+                                        spawn_button(
+                                            keys,
+                                            VersusButton::Answer { player, key },
+                                            Style {
+                                                width: Val::Percent(14.0),
+                                                height: Val::Px(110.0),
+                                                ..default()
+                                            },
+                                            (
+                                                text(name.to_string(), 32., palette.button_text),
+                                                NameLabel,
+                                            ),
+                                            format!("Player {} answer {}", player + 1, name),
+                                            palette,
+                                        );
+                                    }
+                                });
+                        });
+                }
+            });
+        });
+}
+
+pub(super) fn winner_screen_setup(
+    mut commands: Commands,
+    versus: Res<Versus>,
+    assets: TrainerAssets,
+    config: Res<TrainerConfig>,
+    settings: Res<Settings>,
+) {
+    let palette = settings.theme.palette();
+    let font = assets.font();
+    let title = match versus.winner() {
+        Some(player) => format!("Player {} wins!", player + 1),
+        None => "Draw".to_string(),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(12.),
+                    ..config.region_style()
+                },
+                background_color: palette.background.into(),
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            VersusScreen,
+            ScaledUi,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: 48.0,
+                    color: palette.ink,
+                },
+            ));
+            for (index, player) in versus.players.iter().enumerate() {
+                parent.spawn(TextBundle::from_section(
+                    player.summary(index),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 26.0,
+                        color: palette.players[index],
+                    },
+                ));
+            }
+            spawn_button(
+                parent,
+                VersusButton::Quit,
+                Style {
+                    width: Val::Px(320.0),
+                    height: Val::Px(50.0),
+                    ..default()
+                },
+                TextBundle::from_section(
+                    "Back",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: 22.0,
+                        color: palette.button_text,
+                    },
+                ),
+                "Back to the trainer".to_string(),
+                palette,
+            );
+        });
+}
+
+pub(super) fn versus_screen_cleanup(
+    mut commands: Commands,
+    screens: Query<Entity, With<VersusScreen>>,
+) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type VersusButtonQuery<'world, 'state, 'a, 'b, 'c, 'd> = Query<
+    'world,
+    'state,
+    (
+        &'a Interaction,
+        &'b mut UiImage,
+        &'c mut BorderColor,
+        &'d VersusButton,
+    ),
+    (Changed<Interaction>, With<Button>),
+>;
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn versus_button_system(
+    mut commands: Commands,
+    mut interaction_query: VersusButtonQuery,
+    versus: Option<ResMut<Versus>>,
+    mut train: ResMut<TrainSequence>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut answered: EventWriter<NoteAnswered>,
+    mut next_screen: ResMut<NextState<Screen>>,
+) {
+    let Some(mut versus) = versus else {
+        return;
+    };
+    for (interaction, mut image, mut border_color, button) in &mut interaction_query {
+        settings
+            .theme
+            .palette()
+            .paint_button(*interaction, &mut image, &mut border_color);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            VersusButton::Answer { player, key } => {
+                let player = &mut versus.players[player];
+                let Some(answer) = player.train.answer(key) else {
+                    continue;
+                };
+                answered.send(answer);
+                if player.train.next_key == player.train.notes.len() {
+                    let started = player.started.unwrap_or_default();
+                    player.time = Some(time.elapsed() - started);
+                }
+                if versus.players.iter().all(VersusPlayer::finished) {
+                    next_screen.set(Screen::Winner);
+                }
+            }
+            VersusButton::Quit => {
+                commands.remove_resource::<Versus>();
+                train.gen_next(settings.notes_per_round);
+                next_screen.set(Screen::Trainer);
+            }
+        }
+    }
+}
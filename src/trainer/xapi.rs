@@ -0,0 +1,516 @@
+//! Optional xAPI statements for the learning record store (LRS) of a school.
+//! Rounds and lessons are reported as "attempted", "completed" and "scored"
+//! statements. They are queued on the device until the LRS accepts them, so
+//! practising offline loses nothing.
+//!
+//! Reporting is enabled by an `lrs.ron` file next to the saved files:
+//!
+//! ```ron
+//! LrsConfig(
+//!     endpoint: "https://lrs.school.example/xapi",
+//!     username: "key",
+//!     password: "secret",
+//!     accounts: {"Ann": "ann.lee"},
+//! )
+//! ```
+//!
+//! The web build posts through the browser's `fetch`, the others with `ureq`.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    calendar::timestamp,
+    curriculum::{Curriculum, LESSONS},
+    profiles::Profile,
+    versus::Versus,
+    NoteAnswered, TrainSequence,
+};
+use crate::storage;
+
+const CONFIG_KEY: &str = "lrs";
+/// Per profile, the account name made up for the student
+pub(super) const ACCOUNT_KEY: &str = "lrs_account";
+/// Device wide, statements name their student
+const QUEUE_KEY: &str = "lrs_queue";
+const XAPI_VERSION: &str = "1.0.3";
+const VERB_ATTEMPTED: &str = "http://adlnet.gov/expapi/verbs/attempted";
+const VERB_COMPLETED: &str = "http://adlnet.gov/expapi/verbs/completed";
+const VERB_SCORED: &str = "http://adlnet.gov/expapi/verbs/scored";
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct LrsConfig {
+    /// Base URL of the xAPI endpoint, statements go to `<endpoint>/statements`
+    pub endpoint: String,
+    /// Basic authentication, left out when empty
+    pub username: String,
+    pub password: String,
+    /// Identifies the students' accounts, the endpoint when empty
+    pub home_page: String,
+    /// Account names of the students by profile name. Other students get a
+    /// random one kept with their profile, so the "Student" of two devices
+    /// aren't the same actor
+    pub accounts: BTreeMap<String, String>,
+    /// Prefix of the activity ids of the courses and lessons
+    pub activity_base: String,
+    pub batch_size: usize,
+    /// Wait after a failed post, doubled on every failure in a row
+    pub retry_seconds: f32,
+}
+
+impl Default for LrsConfig {
+    fn default() -> Self {
+        LrsConfig {
+            endpoint: String::new(),
+            username: String::new(),
+            password: String::new(),
+            home_page: String::new(),
+            accounts: BTreeMap::new(),
+            activity_base: "urn:staff_trainer".to_string(),
+            batch_size: 50,
+            retry_seconds: 30.,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Account {
+    #[serde(rename = "homePage")]
+    home_page: String,
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Actor {
+    #[serde(rename = "objectType")]
+    object_type: String,
+    name: String,
+    account: Account,
+}
+
+/// Language map, only `en-US` is filled
+type LanguageMap = BTreeMap<String, String>;
+
+fn en_us(text: &str) -> LanguageMap {
+    BTreeMap::from([("en-US".to_string(), text.to_string())])
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Verb {
+    id: String,
+    display: LanguageMap,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct ActivityDefinition {
+    name: LanguageMap,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Activity {
+    #[serde(rename = "objectType")]
+    object_type: String,
+    id: String,
+    definition: ActivityDefinition,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Score {
+    scaled: f32,
+    raw: u32,
+    min: u32,
+    max: u32,
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+struct StatementResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    score: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    success: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completion: Option<bool>,
+    /// ISO 8601 duration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duration: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub(super) struct Statement {
+    id: String,
+    actor: Actor,
+    verb: Verb,
+    object: Activity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<StatementResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+}
+
+/// Random version 4 UUID, statement ids let the LRS drop the duplicates of
+/// a retried batch.
+fn uuid() -> String {
+    let bits: u128 = rand::random();
+    let bits = bits & !(0xf << 76) | (0x4 << 76);
+    let bits = bits & !(0x3 << 62) | (0x2 << 62);
+    let hex = format!("{:032x}", bits);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Connection to the LRS and the statements waiting for it, present when
+/// reporting is configured.
+#[derive(Resource)]
+pub(super) struct Lrs {
+    config: LrsConfig,
+    pub queue: Vec<Statement>,
+    /// Post of the first statements of the queue
+    sending: Option<(usize, PostSlot)>,
+    failures: u32,
+    retry_at: Duration,
+}
+
+impl Lrs {
+    pub fn new(config: LrsConfig) -> Self {
+        Lrs {
+            config,
+            queue: Vec::new(),
+            sending: None,
+            failures: 0,
+            retry_at: Duration::ZERO,
+        }
+    }
+
+    /// The configured LRS with the statements queued by earlier sessions.
+    pub fn load() -> Option<Self> {
+        let contents = storage::read(CONFIG_KEY)?;
+        let config: LrsConfig = match ron::from_str(&contents) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("Invalid LRS configuration, not reporting: {}", err);
+                return None;
+            }
+        };
+        let mut lrs = Lrs::new(config);
+        if let Some(contents) = storage::read(QUEUE_KEY) {
+            match ron::from_str(&contents) {
+                Ok(queue) => lrs.queue = queue,
+                Err(err) => println!("Invalid LRS queue, dropping it: {}", err),
+            }
+        }
+        Some(lrs)
+    }
+
+    fn save_queue(&self) {
+        let result = ron::ser::to_string(&self.queue)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(QUEUE_KEY, &contents));
+        if let Err(err) = result {
+            println!("Failed to save the LRS queue: {}", err);
+        }
+    }
+
+    /// The student's account name at the LRS, from the configuration or made
+    /// up once for the profile.
+    fn account_name(&self, profile: &Profile) -> String {
+        if let Some(name) = self.config.accounts.get(&profile.name) {
+            return name.clone();
+        }
+        let key = profile.key(ACCOUNT_KEY);
+        if let Some(name) = storage::read(&key).and_then(|c| ron::from_str(&c).ok()) {
+            return name;
+        }
+        let name = uuid();
+        let result = ron::to_string(&name)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| storage::write(&key, &contents));
+        if let Err(err) = result {
+            println!(
+                "Failed to save the LRS account of {}: {}",
+                profile.name, err
+            );
+        }
+        name
+    }
+
+    fn statement(
+        &self,
+        profile: &Profile,
+        verb: &str,
+        object: Activity,
+        result: Option<StatementResult>,
+    ) -> Statement {
+        let home_page = if self.config.home_page.is_empty() {
+            &self.config.endpoint
+        } else {
+            &self.config.home_page
+        };
+        Statement {
+            id: uuid(),
+            actor: Actor {
+                object_type: "Agent".to_string(),
+                name: profile.name.clone(),
+                account: Account {
+                    home_page: home_page.clone(),
+                    name: self.account_name(profile),
+                },
+            },
+            verb: Verb {
+                id: verb.to_string(),
+                display: en_us(verb.rsplit('/').next().unwrap_or(verb)),
+            },
+            object,
+            result,
+            timestamp: timestamp(),
+        }
+    }
+
+    /// The lesson or course being drilled.
+    fn activity(&self, train: &TrainSequence) -> Activity {
+        let (kind, id, name) = match train.lesson {
+            Some(lesson) => {
+                let name = LESSONS[lesson].name;
+                ("lesson", name.replace(' ', "_"), name.to_string())
+            }
+            None => {
+                let name = train.course.as_ref();
+                ("course", name.to_string(), name.to_string())
+            }
+        };
+        Activity {
+            object_type: "Activity".to_string(),
+            id: format!("{}:{}:{}", self.config.activity_base, kind, id),
+            definition: ActivityDefinition { name: en_us(&name) },
+        }
+    }
+}
+
+/// ISO 8601 duration of whole and tenth seconds.
+fn iso_duration(duration: Duration) -> String {
+    format!("PT{:.1}S", duration.as_secs_f32())
+}
+
+/// Reports the rounds of the student, from their first answer to their last.
+/// Retry rounds and matches aren't reported, they aren't practice of a course.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn xapi_statement_system(
+    mut answers: EventReader<NoteAnswered>,
+    mut round: Local<Option<(u64, Duration)>>,
+    mut lrs: ResMut<Lrs>,
+    train: Res<TrainSequence>,
+    curriculum: Res<Curriculum>,
+    profile: Option<Res<Profile>>,
+    versus: Option<Res<Versus>>,
+    time: Res<Time>,
+) {
+    for _ in answers.read() {
+        let Some(profile) = profile.as_deref() else {
+            continue;
+        };
+        if train.retry || versus.is_some() {
+            continue;
+        }
+        let now = time.elapsed();
+        let started = match *round {
+            Some((seed, started)) if seed == train.seed => started,
+            _ => {
+                *round = Some((train.seed, now));
+                let statement = lrs.statement(profile, VERB_ATTEMPTED, lrs.activity(&train), None);
+                lrs.queue.push(statement);
+                lrs.save_queue();
+                now
+            }
+        };
+        if train.next_key < train.notes.len() {
+            continue;
+        }
+
+        let max = train.notes.len() as u32;
+        let raw = train.notes.iter().filter(|n| n.is_correct()).count() as u32;
+        let success = train.lesson.and_then(|lesson| {
+            let (result_lesson, _, passed) = curriculum.result?;
+            (result_lesson == lesson).then_some(passed)
+        });
+        let duration = Some(iso_duration(now - started));
+        let completed = StatementResult {
+            completion: Some(true),
+            duration: duration.clone(),
+            ..default()
+        };
+        let scored = StatementResult {
+            score: Some(Score {
+                scaled: raw as f32 / max.max(1) as f32,
+                raw,
+                min: 0,
+                max,
+            }),
+            success,
+            duration,
+            ..default()
+        };
+        let activity = lrs.activity(&train);
+        let completed = lrs.statement(profile, VERB_COMPLETED, activity.clone(), Some(completed));
+        let scored = lrs.statement(profile, VERB_SCORED, activity, Some(scored));
+        lrs.queue.extend([completed, scored]);
+        lrs.save_queue();
+        *round = None;
+    }
+}
+
+/// Outcome of a post, filled in once the request is done.
+type PostSlot = Arc<Mutex<Option<anyhow::Result<PostOutcome>>>>;
+
+enum PostOutcome {
+    Stored,
+    /// The LRS finds the statements malformed, posting them again won't help
+    Rejected(u16),
+}
+
+/// Posts the queued statements in batches, and tries again later when the LRS
+/// can't be reached.
+pub(super) fn xapi_send_system(mut lrs: ResMut<Lrs>, time: Res<Time>) {
+    if let Some((count, slot)) = &lrs.sending {
+        let count = *count;
+        let Some(outcome) = slot.lock().unwrap().take() else {
+            return;
+        };
+        lrs.sending = None;
+        match outcome {
+            Ok(outcome) => {
+                if let PostOutcome::Rejected(status) = outcome {
+                    println!("The LRS rejected {} statements ({})", count, status);
+                }
+                lrs.queue.drain(..count);
+                lrs.save_queue();
+                lrs.failures = 0;
+            }
+            Err(err) => {
+                let delay = Duration::from_secs_f32(lrs.config.retry_seconds)
+                    .saturating_mul(1 << lrs.failures.min(16))
+                    .min(MAX_RETRY_DELAY);
+                println!(
+                    "Failed to post to the LRS, retrying in {:?}: {}",
+                    delay, err
+                );
+                lrs.failures += 1;
+                lrs.retry_at = time.elapsed() + delay;
+            }
+        }
+    }
+
+    if lrs.queue.is_empty() || time.elapsed() < lrs.retry_at {
+        return;
+    }
+    let count = lrs.queue.len().min(lrs.config.batch_size.max(1));
+    let body = match serde_json::to_string(&lrs.queue[..count]) {
+        Ok(body) => body,
+        Err(err) => {
+            println!("Failed to encode statements: {}", err);
+            return;
+        }
+    };
+    let slot = PostSlot::default();
+    spawn_post(lrs.config.clone(), body, slot.clone());
+    lrs.sending = Some((count, slot));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_post(config: LrsConfig, body: String, slot: PostSlot) {
+    bevy::tasks::IoTaskPool::get()
+        .spawn(async move {
+            *slot.lock().unwrap() = Some(post_statements(&config, &body));
+        })
+        .detach();
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn_post(config: LrsConfig, body: String, slot: PostSlot) {
+    wasm_bindgen_futures::spawn_local(async move {
+        *slot.lock().unwrap() = Some(post_statements(&config, &body).await);
+    });
+}
+
+fn outcome(status: u16) -> anyhow::Result<PostOutcome> {
+    match status {
+        200..=299 => Ok(PostOutcome::Stored),
+        // Malformed statements stay malformed, everything else may pass: a
+        // fixed password or endpoint, a proxy back up
+        400 => Ok(PostOutcome::Rejected(status)),
+        401 | 403 => anyhow::bail!("the LRS refused the login ({})", status),
+        404 => anyhow::bail!("no LRS at the endpoint (404)"),
+        _ => anyhow::bail!("LRS error ({})", status),
+    }
+}
+
+fn basic_auth(config: &LrsConfig) -> Option<String> {
+    use base64::Engine;
+
+    if config.username.is_empty() {
+        return None;
+    }
+    let credentials = format!("{}:{}", config.username, config.password);
+    Some(format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn post_statements(config: &LrsConfig, body: &str) -> anyhow::Result<PostOutcome> {
+    let url = format!("{}/statements", config.endpoint.trim_end_matches('/'));
+    let mut request = ureq::post(&url)
+        .timeout(Duration::from_secs(10))
+        .set("Content-Type", "application/json")
+        .set("X-Experience-API-Version", XAPI_VERSION);
+    if let Some(auth) = basic_auth(config) {
+        request = request.set("Authorization", &auth);
+    }
+    match request.send_string(body) {
+        Ok(response) => outcome(response.status()),
+        Err(ureq::Error::Status(status, _)) => outcome(status),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn post_statements(config: &LrsConfig, body: &str) -> anyhow::Result<PostOutcome> {
+    use wasm_bindgen::JsCast;
+
+    let js_error = |err| anyhow::anyhow!("request failed: {:?}", err);
+    let url = format!("{}/statements", config.endpoint.trim_end_matches('/'));
+    let headers = web_sys::Headers::new().map_err(js_error)?;
+    headers
+        .set("Content-Type", "application/json")
+        .map_err(js_error)?;
+    headers
+        .set("X-Experience-API-Version", XAPI_VERSION)
+        .map_err(js_error)?;
+    if let Some(auth) = basic_auth(config) {
+        headers.set("Authorization", &auth).map_err(js_error)?;
+    }
+    let init = web_sys::RequestInit::new();
+    init.set_method("POST");
+    init.set_headers(&headers);
+    init.set_body(&body.into());
+    let window = web_sys::window().ok_or_else(|| anyhow::anyhow!("no window"))?;
+    let response =
+        wasm_bindgen_futures::JsFuture::from(window.fetch_with_str_and_init(&url, &init))
+            .await
+            .map_err(js_error)?;
+    let response: web_sys::Response = response.dyn_into().map_err(js_error)?;
+    outcome(response.status())
+}
@@ -0,0 +1,166 @@
+//! JavaScript API of the web build, for pages embedding the trainer:
+//!
+//! ```js
+//! import init, { start, start_course, on_round_completed } from './dist/staff_trainer.js';
+//!
+//! await init();
+//! on_round_completed((json) => console.log(JSON.parse(json).accuracy));
+//! try {
+//!     start('#trainer');
+//! } catch (error) {
+//!     // winit leaves its event loop with an exception, the trainer keeps running
+//! }
+//! start_course('BassLines', 'week3');
+//! ```
+//!
+//! Seeds are passed as text, JavaScript numbers can't hold every `u64`.
+
+use std::cell::RefCell;
+
+use bevy::prelude::*;
+use wasm_bindgen::prelude::*;
+
+use crate::trainer::{HostCommand, LaunchOptions, RoundCompleted};
+
+/// What the page sent, waiting for the next frame.
+#[derive(Default)]
+struct Page {
+    commands: Vec<HostCommand>,
+    listeners: Vec<js_sys::Function>,
+}
+
+thread_local! {
+    static PAGE: RefCell<Page> = RefCell::default();
+}
+
+fn send(command: HostCommand) {
+    PAGE.with_borrow_mut(|page| page.commands.push(command));
+}
+
+/// The drill linked to, like `?course=BassLines&notes=24&seed=abc`.
+fn page_launch_options() -> LaunchOptions {
+    let search = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+    let Ok(parameters) = web_sys::UrlSearchParams::new_with_str(&search) else {
+        return default();
+    };
+    let values: Vec<_> = LaunchOptions::NAMES
+        .into_iter()
+        .filter_map(|name| Some((name, parameters.get(name)?)))
+        .collect();
+    LaunchOptions::parse(values.iter().map(|(name, value)| (*name, value.as_str()))).unwrap_or_else(
+        |err| {
+            println!("Ignoring the link's drill: {}", err);
+            default()
+        },
+    )
+}
+
+/// Starts the trainer in the canvas matching the CSS selector, or on the
+/// whole page without one. The page URL can name the drill to start with.
+#[wasm_bindgen]
+pub fn start(canvas: Option<String>) {
+    let mut window = crate::default_window();
+    if let Some(canvas) = canvas {
+        window.canvas = Some(canvas);
+        window.fit_canvas_to_parent = true;
+        window.mode = bevy::window::WindowMode::Windowed;
+    }
+    let launch = page_launch_options();
+    crate::build_app_with(window, |app| {
+        app.insert_resource(launch).add_systems(Update, page_system);
+    })
+    .run();
+}
+
+/// Starts a round of the course named like its button, from the seed when
+/// given.
+#[wasm_bindgen]
+pub fn start_course(course: String, seed: Option<String>) {
+    send(HostCommand::Start { course, seed });
+}
+
+/// Changes the settings named in a JSON object, like `{"sound": false}`.
+#[wasm_bindgen]
+pub fn set_settings(json: String) {
+    send(HostCommand::Settings(json));
+}
+
+#[wasm_bindgen]
+pub fn pause() {
+    send(HostCommand::Pause);
+}
+
+#[wasm_bindgen]
+pub fn resume() {
+    send(HostCommand::Resume);
+}
+
+/// Imports an assignment into the homework of the current profile, from the
+/// RON text of its file.
+#[wasm_bindgen]
+pub fn import_homework(ron: String) {
+    send(HostCommand::ImportHomework(ron));
+}
+
+/// Asks the student for an assignment file and imports it.
+pub(crate) fn pick_homework() {
+    if let Err(err) = try_pick_homework() {
+        println!("Can't pick a file: {:?}", err);
+    }
+}
+
+fn try_pick_homework() -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or("no document")?;
+    let input: web_sys::HtmlInputElement = document.create_element("input")?.dyn_into()?;
+    input.set_type("file");
+    input.set_accept(".ron");
+    let picked = input.clone();
+    let on_change = Closure::once(move |_: web_sys::Event| {
+        let Some(file) = picked.files().and_then(|files| files.get(0)) else {
+            return;
+        };
+        let on_text = Closure::once(|text: JsValue| {
+            send(HostCommand::ImportHomework(
+                text.as_string().unwrap_or_default(),
+            ));
+        });
+        let _ = file.text().then(&on_text);
+        on_text.forget();
+    });
+    input.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+    on_change.forget();
+    input.click();
+    Ok(())
+}
+
+/// Calls back with the JSON results of every finished round.
+#[wasm_bindgen]
+pub fn on_round_completed(callback: js_sys::Function) {
+    PAGE.with_borrow_mut(|page| page.listeners.push(callback));
+}
+
+/// Passes the page's commands on and reports the finished rounds.
+fn page_system(mut commands: EventWriter<HostCommand>, mut rounds: EventReader<RoundCompleted>) {
+    // Listeners may call back into the API, the page isn't borrowed meanwhile
+    let (pending, listeners) =
+        PAGE.with_borrow_mut(|page| (std::mem::take(&mut page.commands), page.listeners.clone()));
+    commands.send_batch(pending);
+    for round in rounds.read() {
+        let json = match serde_json::to_string(round) {
+            Ok(json) => JsValue::from_str(&json),
+            Err(err) => {
+                println!("Failed to encode the round: {}", err);
+                continue;
+            }
+        };
+        for listener in &listeners {
+            if let Err(err) = listener.call1(&JsValue::NULL, &json) {
+                println!("Round listener failed: {:?}", err);
+            }
+        }
+    }
+}